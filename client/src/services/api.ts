@@ -3,10 +3,15 @@ import type {
   HealthResponse,
   ISSData,
   ISSTrend,
+  OSDRFacetsParams,
+  OSDRFacetsResponse,
   OSDRItem,
   OSDRQueryParams,
   OSDRResponse,
+  OSDRSyncJob,
+  OSDRSyncStarted,
   SpaceCache,
+  SpaceSource,
   SpaceSummary,
 } from "../types/api";
 
@@ -22,6 +27,11 @@ const api = axios.create({
 const unwrap = <T>(p: Promise<AxiosResponse<T>>): Promise<T> =>
   p.then((r) => r.data);
 
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+// Сколько ждать окончания синхронизации OSDR, прежде чем сдаться.
+const OSDR_SYNC_TIMEOUT_MS = 10 * 60 * 1000;
+
 export const apiService = {
   health: (): Promise<HealthResponse> => unwrap(api.get("/health")),
 
@@ -29,7 +39,19 @@ export const apiService = {
   triggerISSFetch: (): Promise<ISSData> => unwrap(api.get("/fetch")),
   getISSTrend: (): Promise<ISSTrend> => unwrap(api.get("/iss/trend")),
 
-  syncOSDR: (): Promise<{ written: number }> => unwrap(api.get("/osdr/sync")),
+  syncOSDR: async (): Promise<OSDRSyncJob> => {
+    const started: OSDRSyncStarted = await unwrap(api.get("/osdr/sync"));
+    const deadline = Date.now() + OSDR_SYNC_TIMEOUT_MS;
+    while (Date.now() < deadline) {
+      const job: OSDRSyncJob = await unwrap(
+        api.get(`/osdr/sync/${started.job_id}`),
+      );
+      if (job.status === "failed") throw new Error(job.error ?? "OSDR sync failed");
+      if (job.status === "ok") return job;
+      await sleep(1000);
+    }
+    throw new Error(`OSDR sync #${started.job_id} did not finish in time`);
+  },
 
   getOSDRList: (params?: OSDRQueryParams | number): Promise<OSDRResponse> => {
     let requestParams: OSDRQueryParams;
@@ -45,10 +67,15 @@ export const apiService = {
     return unwrap(api.get("/osdr/list", { params: requestParams }));
   },
 
+  getOSDRFacets: (params?: OSDRFacetsParams): Promise<OSDRFacetsResponse> =>
+    unwrap(api.get("/osdr/facets", { params })),
+
   getSpaceData: (source: string): Promise<SpaceCache> =>
     unwrap(api.get(`/space/${encodeURIComponent(source)}/latest`)),
   refreshSpaceData: (sources: string[]): Promise<{ refreshed: string[] }> =>
     unwrap(api.get("/space/refresh", { params: { src: sources.join(",") } })),
   getSpaceSummary: (): Promise<SpaceSummary> =>
     unwrap(api.get("/space/summary")),
+  getSpaceSources: (): Promise<{ sources: SpaceSource[] }> =>
+    unwrap(api.get("/space/sources")),
 };
@@ -43,6 +43,18 @@ export interface SpaceCache {
   source: string;
   fetched_at: string;
   payload: any;
+  age_seconds: number | null;
+  stale: boolean;
+}
+
+export interface SpaceSource {
+  name: string;
+  description: string;
+  interval_seconds: number;
+  last_fetched_at: string | null;
+  rows: number;
+  age_seconds: number | null;
+  stale: boolean;
 }
 
 export interface SpaceSummary {
@@ -59,8 +71,57 @@ export interface OSDRQueryParams {
   limit?: number;
   sort_by?: string;
   order?: "asc" | "desc";
+  cursor?: string;
 }
 
 export interface OSDRResponse {
   items: OSDRItem[];
+  total: number;
+  limit: number;
+  next_cursor: string | null;
+}
+
+export interface OSDRSyncStarted {
+  job_id: number;
+  status: "running";
+  coalesced: boolean;
+  status_url: string;
+}
+
+export interface OSDRSyncJob {
+  id: number;
+  status: "running" | "ok" | "failed";
+  phase: "downloading" | "writing" | "removing" | "done" | null;
+  progress: { processed: number; total: number | null };
+  heartbeat_at: string;
+  error: string | null;
+  total: number;
+  added: number;
+  modified: number;
+  removed: number;
+  unchanged: number;
+}
+
+export interface OSDRFacetValue<T = string> {
+  value: T | null;
+  count: number;
+}
+
+export interface OSDRFacetsResponse {
+  total: number;
+  facets: {
+    status: OSDRFacetValue[];
+    organism: OSDRFacetValue[];
+    assay_type: OSDRFacetValue[];
+    mission: OSDRFacetValue[];
+    release_year: OSDRFacetValue<number>[];
+  };
+}
+
+export interface OSDRFacetsParams {
+  q?: string;
+  field?: "all" | "dataset_id" | "title" | "description";
+  status?: string;
+  organism?: string;
+  updated_after?: string;
 }
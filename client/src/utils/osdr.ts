@@ -3,9 +3,9 @@ export const extractOSDRData = (item: any) => {
 
   return {
     dataset_id: item.dataset_id || "N/A",
-    title: item.dataset_id || "Без названия",
-    status: "available",
-    updated_at: item.updated_at || null,
+    title: item.title || item.dataset_id || "Без названия",
+    status: item.status || "available",
+    updated_at: item.upstream_updated_at || item.updated_at || null,
     rest_url: raw.REST_URL || "",
   };
 };
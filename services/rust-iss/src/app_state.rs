@@ -1,17 +1,39 @@
 use sqlx::PgPool;
 use deadpool_redis::Pool; 
 
+use std::sync::Arc;
+
+use crate::services::iss_providers::IssProvider;
+use crate::services::retention_service::RetentionConfig;
+
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
     pub redis: Pool, 
     pub nasa_url: String,          // OSDR
     pub nasa_key: String,          // ключ NASA
-    pub fallback_url: String,      // ISS 
+    pub iss_providers: Vec<IssProvider>, // ISS, в порядке failover
+    pub satellites: Vec<i32>,      // NORAD ID, опрашиваемые фоновым циклом
     pub every_osdr: u64,
+    pub every_osdr_enrich: u64,
+    pub osdr_enrich_concurrency: usize, // параллельных запросов к REST_URL
+    pub osdr_reenrich_days: i64,   // метаданные старше — перечитать; 0 — только при изменении raw
     pub every_iss: u64,
+    pub every_altitude: u64,
+    pub every_compact: u64,
+    pub compact_after_days: i64,   // сырые строки старше — в агрегаты
+    pub compact_archive: bool,     // true — переносить в архив, false — удалять
+    pub every_retention: u64,
+    pub retention: Arc<RetentionConfig>, // политики хранения space_cache / iss_fetch_log
+    pub retention_dry_run: bool,   // true — только отчёт, без удаления
+    pub every_gapfill: u64,
+    pub gapfill_lookback_hours: i64, // насколько назад искать дыры в истории ISS
     pub every_apod: u64,
     pub every_neo: u64,
     pub every_donki: u64,
     pub every_spacex: u64,
+    pub cache_ttl_summary: u64,    // TTL ответов в Redis, секунды; 0 — без кэша
+    pub cache_ttl_latest: u64,
+    pub cache_ttl_last: u64,
+    pub cache_ttl_osdr_list: u64,
 }
\ No newline at end of file
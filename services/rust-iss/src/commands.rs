@@ -0,0 +1,46 @@
+use crate::app_state::AppState;
+use crate::services::gapfill_service::fill_gaps;
+use crate::services::iss_service::{backfill_positions, geocode_positions};
+use crate::services::osdr_service::validate_mappings;
+use crate::services::retention_service::run_retention;
+use crate::utils::mapping::load_configured;
+
+/// Одноразовые команды обслуживания: `rust_iss <command>`.
+pub async fn run(cmd: &str, st: &AppState) -> anyhow::Result<()> {
+    match cmd {
+        "backfill-positions" => {
+            let report = backfill_positions(&st.pool, None).await?;
+            for f in &report.failed {
+                tracing::warn!("iss_fetch_log {}: {}", f.fetch_id, f.reason);
+            }
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+        "geocode-positions" => {
+            let updated = geocode_positions(&st.pool).await?;
+            println!("{}", serde_json::json!({ "updated": updated }));
+            Ok(())
+        }
+        "fill-gaps" => {
+            for &sat in &st.satellites {
+                let report = fill_gaps(&st.pool, &st.iss_providers, sat, st.every_iss, st.gapfill_lookback_hours).await?;
+                println!("{}", serde_json::json!({ "satellite_id": sat, "report": report }));
+            }
+            Ok(())
+        }
+        "validate-mappings" => {
+            let report = validate_mappings(&st.pool, &load_configured()?).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            match report.failing() {
+                0 => Ok(()),
+                n => anyhow::bail!("{n} field mapping problem(s), see report above"),
+            }
+        }
+        "retention" | "retention-dry-run" => {
+            let report = run_retention(st, cmd == "retention-dry-run").await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+        _ => anyhow::bail!("unknown command {cmd}; available: backfill-positions, geocode-positions, fill-gaps, validate-mappings, retention, retention-dry-run"),
+    }
+}
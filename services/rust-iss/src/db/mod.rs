@@ -10,6 +10,126 @@ pub async fn init_db(pool: &PgPool) -> anyhow::Result<()> {
             payload JSONB NOT NULL
         )"
     ).execute(pool).await?;
+    sqlx::query("ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS satellite_id INTEGER NOT NULL DEFAULT 25544").execute(pool).await?;
+    sqlx::query("ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS provider TEXT").execute(pool).await?;
+    // payload — ответ апстрима как есть, normalized — поля позиции в формате wheretheiss;
+    // у старых строк normalized пуст, а payload уже нормализован
+    sqlx::query("ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS normalized JSONB").execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS ix_iss_fetch_log_satellite ON iss_fetch_log(satellite_id, id DESC)").execute(pool).await?;
+
+    // нормализованные позиции (из payload iss_fetch_log)
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iss_positions(
+            id BIGSERIAL PRIMARY KEY,
+            fetch_id BIGINT UNIQUE REFERENCES iss_fetch_log(id) ON DELETE CASCADE,
+            satellite_id INTEGER NOT NULL DEFAULT 25544,
+            observed_at TIMESTAMPTZ NOT NULL,
+            lat DOUBLE PRECISION NOT NULL,
+            lon DOUBLE PRECISION NOT NULL,
+            altitude DOUBLE PRECISION,
+            velocity DOUBLE PRECISION,
+            visibility TEXT,
+            footprint DOUBLE PRECISION
+        )"
+    ).execute(pool).await?;
+    sqlx::query("ALTER TABLE iss_positions ADD COLUMN IF NOT EXISTS anomaly TEXT").execute(pool).await?;
+    // офлайн-геокодинг подспутниковой точки
+    sqlx::query("ALTER TABLE iss_positions ADD COLUMN IF NOT EXISTS place TEXT").execute(pool).await?;
+    sqlx::query("ALTER TABLE iss_positions ADD COLUMN IF NOT EXISTS place_iso TEXT").execute(pool).await?;
+    sqlx::query("ALTER TABLE iss_positions ADD COLUMN IF NOT EXISTS place_kind TEXT").execute(pool).await?;
+    // live — обычный опрос, backfilled — дозаполнение дыр
+    sqlx::query("ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS origin TEXT NOT NULL DEFAULT 'live'").execute(pool).await?;
+    sqlx::query("ALTER TABLE iss_positions ADD COLUMN IF NOT EXISTS origin TEXT NOT NULL DEFAULT 'live'").execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS ix_iss_positions_observed_at ON iss_positions(observed_at)").execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS ix_iss_positions_satellite ON iss_positions(satellite_id, observed_at DESC)").execute(pool).await?;
+
+    // агрегаты и архив после компактизации iss_fetch_log
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iss_position_hourly(
+            satellite_id INTEGER NOT NULL,
+            bucket TIMESTAMPTZ NOT NULL,
+            samples BIGINT NOT NULL,
+            min_alt DOUBLE PRECISION,
+            max_alt DOUBLE PRECISION,
+            avg_alt DOUBLE PRECISION,
+            min_vel DOUBLE PRECISION,
+            max_vel DOUBLE PRECISION,
+            avg_vel DOUBLE PRECISION,
+            first_at TIMESTAMPTZ NOT NULL,
+            first_lat DOUBLE PRECISION NOT NULL,
+            first_lon DOUBLE PRECISION NOT NULL,
+            last_at TIMESTAMPTZ NOT NULL,
+            last_lat DOUBLE PRECISION NOT NULL,
+            last_lon DOUBLE PRECISION NOT NULL,
+            PRIMARY KEY(satellite_id, bucket)
+        )"
+    ).execute(pool).await?;
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iss_position_daily(
+            satellite_id INTEGER NOT NULL,
+            bucket TIMESTAMPTZ NOT NULL,
+            samples BIGINT NOT NULL,
+            min_alt DOUBLE PRECISION,
+            max_alt DOUBLE PRECISION,
+            avg_alt DOUBLE PRECISION,
+            min_vel DOUBLE PRECISION,
+            max_vel DOUBLE PRECISION,
+            avg_vel DOUBLE PRECISION,
+            first_at TIMESTAMPTZ NOT NULL,
+            first_lat DOUBLE PRECISION NOT NULL,
+            first_lon DOUBLE PRECISION NOT NULL,
+            last_at TIMESTAMPTZ NOT NULL,
+            last_lat DOUBLE PRECISION NOT NULL,
+            last_lon DOUBLE PRECISION NOT NULL,
+            PRIMARY KEY(satellite_id, bucket)
+        )"
+    ).execute(pool).await?;
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iss_fetch_log_archive(
+            id BIGINT PRIMARY KEY,
+            fetched_at TIMESTAMPTZ NOT NULL,
+            satellite_id INTEGER NOT NULL,
+            record JSONB NOT NULL,
+            archived_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )"
+    ).execute(pool).await?;
+
+    // манёвры подъёма орбиты
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iss_reboost_events(
+            id BIGSERIAL PRIMARY KEY,
+            satellite_id INTEGER NOT NULL,
+            day DATE NOT NULL,
+            before_km DOUBLE PRECISION NOT NULL,
+            after_km DOUBLE PRECISION NOT NULL,
+            delta_km DOUBLE PRECISION NOT NULL,
+            detected_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            UNIQUE(satellite_id, day)
+        )"
+    ).execute(pool).await?;
+
+    // геозоны ISS
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iss_regions(
+            id BIGSERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            geometry JSONB NOT NULL,
+            inside BOOLEAN NOT NULL DEFAULT false,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )"
+    ).execute(pool).await?;
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iss_region_events(
+            id BIGSERIAL PRIMARY KEY,
+            region_id BIGINT NOT NULL REFERENCES iss_regions(id) ON DELETE CASCADE,
+            fetch_id BIGINT,
+            event TEXT NOT NULL,
+            occurred_at TIMESTAMPTZ NOT NULL,
+            lat DOUBLE PRECISION NOT NULL,
+            lon DOUBLE PRECISION NOT NULL
+        )"
+    ).execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS ix_iss_region_events_region ON iss_region_events(region_id, occurred_at DESC)").execute(pool).await?;
 
     // OSDR
     sqlx::query(
@@ -27,6 +147,73 @@ pub async fn init_db(pool: &PgPool) -> anyhow::Result<()> {
         "CREATE UNIQUE INDEX IF NOT EXISTS ux_osdr_dataset_id
          ON osdr_items(dataset_id) WHERE dataset_id IS NOT NULL"
     ).execute(pool).await?;
+    // датасет пропал из апстрима — помечаем, а не удаляем
+    sqlx::query("ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS removed_at TIMESTAMPTZ").execute(pool).await?;
+    // метаданные из REST_URL датасета
+    for col in [
+        "description TEXT", "organism TEXT", "assay_types TEXT[]", "mission TEXT",
+        "release_date TIMESTAMPTZ", "upstream_updated_at TIMESTAMPTZ", "metadata JSONB", "enriched_at TIMESTAMPTZ",
+        // последняя неудачная попытка обогащения: от числа неудач зависит пауза до следующей
+        "enrich_error TEXT", "enrich_attempted_at TIMESTAMPTZ", "enrich_failures INT NOT NULL DEFAULT 0",
+    ] {
+        sqlx::query(&format!("ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS {col}")).execute(pool).await?;
+    }
+    // полнотекстовый поиск: id (A), заголовок (B), описание (C)
+    sqlx::query(
+        "ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS search_tsv tsvector GENERATED ALWAYS AS (
+            setweight(to_tsvector('simple', coalesce(dataset_id, '')), 'A') ||
+            setweight(to_tsvector('english', coalesce(title, '')), 'B') ||
+            setweight(to_tsvector('english', coalesce(description, '')), 'C')
+        ) STORED"
+    ).execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS ix_osdr_items_search ON osdr_items USING GIN(search_tsv)").execute(pool).await?;
+
+    // история изменений датасетов: added | changed | restored | removed | metadata
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS osdr_item_history(
+            id BIGSERIAL PRIMARY KEY,
+            dataset_id TEXT NOT NULL,
+            change TEXT NOT NULL,
+            changed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            diff JSONB
+        )"
+    ).execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS ix_osdr_item_history_dataset ON osdr_item_history(dataset_id, changed_at DESC)").execute(pool).await?;
+
+    // отчёт по каждому прогону синхронизации OSDR
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS osdr_syncs(
+            id BIGSERIAL PRIMARY KEY,
+            started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            finished_at TIMESTAMPTZ,
+            status TEXT NOT NULL DEFAULT 'running',
+            error TEXT,
+            source_url TEXT NOT NULL,
+            total INTEGER NOT NULL DEFAULT 0,
+            added INTEGER NOT NULL DEFAULT 0,
+            modified INTEGER NOT NULL DEFAULT 0,
+            removed INTEGER NOT NULL DEFAULT 0,
+            unchanged INTEGER NOT NULL DEFAULT 0,
+            added_ids TEXT[] NOT NULL DEFAULT '{}',
+            modified_ids TEXT[] NOT NULL DEFAULT '{}',
+            removed_ids TEXT[] NOT NULL DEFAULT '{}'
+        )"
+    ).execute(pool).await?;
+    sqlx::query("ALTER TABLE osdr_syncs ADD COLUMN IF NOT EXISTS load_ms BIGINT").execute(pool).await?;
+    sqlx::query("ALTER TABLE osdr_syncs ADD COLUMN IF NOT EXISTS rows_per_sec DOUBLE PRECISION").execute(pool).await?;
+    // фаза и прогресс задания: downloading -> writing -> removing -> done
+    sqlx::query("ALTER TABLE osdr_syncs ADD COLUMN IF NOT EXISTS phase TEXT").execute(pool).await?;
+    sqlx::query("ALTER TABLE osdr_syncs ADD COLUMN IF NOT EXISTS processed INTEGER NOT NULL DEFAULT 0").execute(pool).await?;
+    sqlx::query("ALTER TABLE osdr_syncs ADD COLUMN IF NOT EXISTS expected INTEGER").execute(pool).await?;
+    // живое задание обновляет его вместе с прогрессом; давно не обновлявшееся — брошено
+    sqlx::query("ALTER TABLE osdr_syncs ADD COLUMN IF NOT EXISTS heartbeat_at TIMESTAMPTZ NOT NULL DEFAULT now()").execute(pool).await?;
+    // одновременно идёт не больше одной синхронизации; старые зависшие перед индексом закрываем
+    sqlx::query(
+        "UPDATE osdr_syncs SET status = 'failed', error = 'interrupted', finished_at = now()
+         WHERE status = 'running' AND id < (SELECT max(id) FROM osdr_syncs WHERE status = 'running')"
+    ).execute(pool).await?;
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS ux_osdr_syncs_running ON osdr_syncs(status) WHERE status = 'running'").execute(pool).await?;
+    sqlx::query("ALTER TABLE osdr_item_history ADD COLUMN IF NOT EXISTS sync_id BIGINT REFERENCES osdr_syncs(id) ON DELETE SET NULL").execute(pool).await?;
 
     //  кэш космоданных
     sqlx::query(
@@ -38,6 +225,8 @@ pub async fn init_db(pool: &PgPool) -> anyhow::Result<()> {
         )"
     ).execute(pool).await?;
     sqlx::query("CREATE INDEX IF NOT EXISTS ix_space_cache_source ON space_cache(source,fetched_at DESC)").execute(pool).await?;
+    // последняя запись по источнику (DISTINCT ON в сводке)
+    sqlx::query("CREATE INDEX IF NOT EXISTS ix_space_cache_source_id ON space_cache(source, id DESC)").execute(pool).await?;
 
     Ok(())
 }
\ No newline at end of file
@@ -2,7 +2,7 @@
 
 
 use std::time::Duration;
-use axum::{Router, routing::get};
+use axum::{Router, routing::{get, delete}};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
 use middleware::redis_noop::redis_noop;
@@ -11,6 +11,7 @@ use deadpool_redis::Pool;
 
 
 mod app_state;
+mod commands;
 mod db;
 mod routes;
 mod services;
@@ -19,8 +20,14 @@ mod middleware;
 
 use app_state::AppState;
 use db::init_db;
-use services::iss_service::fetch_and_store_iss;
-use services::osdr_service::fetch_and_store_osdr;
+use services::altitude_service::refresh_reboost_events;
+use services::cache_service::invalidate;
+use services::compaction_service::compact_iss_log;
+use services::gapfill_service::fill_gaps;
+use services::iss_providers::providers_from_env;
+use services::iss_service::{fetch_and_store_iss, ISS_NORAD_ID};
+use services::retention_service::{compaction_conflicts, load_retention_config, run_retention};
+use services::osdr_service::{enrich_osdr, fail_interrupted_syncs, fetch_and_store_osdr};
 use services::space_cache_service::{
     fetch_apod, fetch_neo_feed, fetch_donki_flr, fetch_donki_cme, fetch_spacex_next
 };
@@ -52,13 +59,40 @@ async fn main() -> anyhow::Result<()> {
 
     let fallback_url = std::env::var("WHERE_ISS_URL")
         .unwrap_or_else(|_| "https://api.wheretheiss.at/v1/satellites/25544".to_string());
+    let satellite_url = std::env::var("SATELLITE_URL_TEMPLATE")
+        .unwrap_or_else(|_| "https://api.wheretheiss.at/v1/satellites/{id}".to_string());
+    let satellites = env_ids("SATELLITE_IDS", ISS_NORAD_ID);
+    let iss_providers = providers_from_env(&fallback_url, &satellite_url)?;
 
     let every_osdr   = env_u64("FETCH_EVERY_SECONDS", 600);
+    let every_osdr_enrich = env_u64("OSDR_ENRICH_EVERY_SECONDS", 600);
+    let osdr_enrich_concurrency = env_u64("OSDR_ENRICH_CONCURRENCY", 4) as usize;
+    let osdr_reenrich_days = env_u64("OSDR_REENRICH_DAYS", 7) as i64;
     let every_iss    = env_u64("ISS_EVERY_SECONDS",   120);
+    let every_alt    = env_u64("ALTITUDE_EVERY_SECONDS", 3600);
+    let every_compact = env_u64("COMPACT_EVERY_SECONDS", 3600);
+    let compact_after_days = env_u64("COMPACT_AFTER_DAYS", 30) as i64;
+    let compact_archive = std::env::var("COMPACT_MODE").map(|m| m == "archive").unwrap_or(false);
+    let every_retention = env_u64("RETENTION_EVERY_SECONDS", 3600);
+    let retention_dry_run = std::env::var("RETENTION_DRY_RUN").map(|v| v == "true" || v == "1").unwrap_or(false);
+    let retention = std::sync::Arc::new(load_retention_config()?);
+    let conflicts = compaction_conflicts(&retention, compact_after_days);
+    if !conflicts.is_empty() {
+        anyhow::bail!(
+            "retention: iss_fetch_log/{} would delete raw rows before compaction; keep_within_days must be at least COMPACT_AFTER_DAYS + 1 ({})",
+            conflicts.join(", "), compact_after_days + 1
+        );
+    }
+    let every_gapfill = env_u64("GAPFILL_EVERY_SECONDS", 900);
+    let gapfill_lookback_hours = env_u64("GAPFILL_LOOKBACK_HOURS", 48) as i64;
     let every_apod   = env_u64("APOD_EVERY_SECONDS",  43200); // 12ч
     let every_neo    = env_u64("NEO_EVERY_SECONDS",   7200);  // 2ч
     let every_donki  = env_u64("DONKI_EVERY_SECONDS", 3600);  // 1ч
     let every_spacex = env_u64("SPACEX_EVERY_SECONDS",3600);
+    let cache_ttl_summary = env_u64("CACHE_TTL_SUMMARY_SECONDS", 60);
+    let cache_ttl_latest = env_u64("CACHE_TTL_LATEST_SECONDS", 300);
+    let cache_ttl_last = env_u64("CACHE_TTL_LAST_SECONDS", 120);
+    let cache_ttl_osdr_list = env_u64("CACHE_TTL_OSDR_LIST_SECONDS", 300);
 
     let pool = sqlx::postgres::PgPoolOptions::new()
         .max_connections(5)
@@ -71,10 +105,28 @@ async fn main() -> anyhow::Result<()> {
         redis: redis_pool,
         nasa_url: nasa_url.clone(),
         nasa_key,
-        fallback_url: fallback_url.clone(),
-        every_osdr, every_iss, every_apod, every_neo, every_donki, every_spacex,
+        iss_providers,
+        satellites,
+        every_osdr, every_osdr_enrich, osdr_enrich_concurrency, osdr_reenrich_days,
+        every_iss, every_altitude: every_alt,
+        every_compact, compact_after_days, compact_archive,
+        every_retention, retention, retention_dry_run,
+        every_gapfill, gapfill_lookback_hours,
+        every_apod, every_neo, every_donki, every_spacex,
+        cache_ttl_summary, cache_ttl_latest, cache_ttl_last, cache_ttl_osdr_list,
     };
 
+    // одноразовые команды, например `rust_iss backfill-positions`
+    if let Some(cmd) = std::env::args().nth(1) {
+        return commands::run(&cmd, &state).await;
+    }
+
+    match fail_interrupted_syncs(&state.pool).await {
+        Ok(0) => {}
+        Ok(n) => tracing::warn!("OSDR: {n} abandoned sync job(s) marked failed"),
+        Err(e) => tracing::error!("osdr interrupted syncs err {e:?}"),
+    }
+
     // фон OSDR
     {
         let st = state.clone();
@@ -87,18 +139,100 @@ async fn main() -> anyhow::Result<()> {
             }
         });
     }
+    // фон обогащение OSDR метаданными
+    {
+        let st = state.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = enrich_osdr(&st).await {
+                    tracing::error!("osdr enrich err {e:?}")
+                }
+                tokio::time::sleep(Duration::from_secs(st.every_osdr_enrich)).await;
+            }
+        });
+    }
     // фон ISS
     {
         let st = state.clone();
         tokio::spawn(async move {
             loop {
-                if let Err(e) = fetch_and_store_iss(&st.pool, &st.fallback_url).await { 
-                    tracing::error!("iss err {e:?}") 
+                for &sat in &st.satellites {
+                    match fetch_and_store_iss(&st.pool, &st.iss_providers, sat).await {
+                        Ok(()) => invalidate(&st.redis, &["space:summary", &format!("iss:last:{sat}")]).await,
+                        Err(e) => tracing::error!("iss err (norad {sat}) {e:?}"),
+                    }
                 }
                 tokio::time::sleep(Duration::from_secs(st.every_iss)).await;
             }
         });
     }
+    // фон манёвры орбиты
+    {
+        let st = state.clone();
+        tokio::spawn(async move {
+            loop {
+                for &sat in &st.satellites {
+                    if let Err(e) = refresh_reboost_events(&st.pool, sat, 365).await { 
+                        tracing::error!("reboost err (norad {sat}) {e:?}") 
+                    }
+                }
+                tokio::time::sleep(Duration::from_secs(st.every_altitude)).await;
+            }
+        });
+    }
+    // фон компактизация журнала ISS
+    {
+        let st = state.clone();
+        tokio::spawn(async move {
+            loop {
+                match compact_iss_log(&st.pool, st.compact_after_days, st.compact_archive).await {
+                    Ok(r) if r.days > 0 => tracing::info!(
+                        "iss compaction: {} days, {} raw rows ({} positions backfilled, {} unparsable)",
+                        r.days, r.deleted, r.backfilled, r.unparsable
+                    ),
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("iss compaction err {e:?}"),
+                }
+                tokio::time::sleep(Duration::from_secs(st.every_compact)).await;
+            }
+        });
+    }
+    // фон чистка space_cache / iss_fetch_log по политикам хранения
+    {
+        let st = state.clone();
+        tokio::spawn(async move {
+            loop {
+                match run_retention(&st, st.retention_dry_run).await {
+                    Ok(r) if r.dry_run && r.deleted > 0 => for p in &r.policies {
+                        tracing::info!("retention dry-run: {}/{} would delete {} rows", p.table, p.source.as_deref().unwrap_or("-"), p.deleted);
+                    },
+                    Ok(r) if r.deleted > 0 => tracing::info!("retention: {} rows in {} policies", r.deleted, r.policies.len()),
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("retention err {e:?}"),
+                }
+                tokio::time::sleep(Duration::from_secs(st.every_retention)).await;
+            }
+        });
+    }
+    // фон дозаполнение дыр в истории ISS
+    {
+        let st = state.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(st.every_gapfill)).await;
+                for &sat in &st.satellites {
+                    match fill_gaps(&st.pool, &st.iss_providers, sat, st.every_iss, st.gapfill_lookback_hours).await {
+                        Ok(r) if r.gaps > 0 => tracing::info!(
+                            "iss gap fill (norad {sat}): {} gaps, {} from provider, {} propagated, {} skipped",
+                            r.gaps, r.from_provider, r.propagated, r.skipped
+                        ),
+                        Ok(_) => {}
+                        Err(e) => tracing::error!("gap fill err (norad {sat}) {e:?}"),
+                    }
+                }
+            }
+        });
+    }
     // фон APOD
     {
         let st = state.clone();
@@ -158,13 +292,30 @@ async fn main() -> anyhow::Result<()> {
     .route("/last", get(routes::iss::last_iss))
     .route("/fetch", get(routes::iss::trigger_iss))
     .route("/iss/trend", get(routes::iss::iss_trend))
+    .route("/iss/history", get(routes::iss::iss_history))
+    .route("/iss/track", get(routes::iss::iss_track))
+    .route("/iss/places", get(routes::iss::iss_places))
+    .route("/sun/terminator", get(routes::sun::terminator))
+    .route("/iss/altitude/summary", get(routes::altitude::altitude_summary))
+    .route("/iss/altitude/reboosts", get(routes::altitude::reboost_events))
+    .route("/iss/regions", get(routes::regions::list_regions).post(routes::regions::create_region))
+    .route("/iss/regions/:id", delete(routes::regions::delete_region))
+    .route("/iss/regions/:id/events", get(routes::regions::region_events))
     // OSDR
-    .route("/osdr/sync", get(routes::osdr::osdr_sync))
+    .route("/osdr/sync", get(routes::osdr::osdr_sync).post(routes::osdr::osdr_sync))
+    .route("/osdr/sync/:job_id", get(routes::osdr::osdr_sync_job))
     .route("/osdr/list", get(routes::osdr::osdr_list))
+    .route("/osdr/search", get(routes::osdr::osdr_search))
+    .route("/osdr/facets", get(routes::osdr::osdr_facets))
+    .route("/osdr/export", get(routes::osdr::osdr_export))
+    .route("/osdr/syncs", get(routes::osdr::osdr_syncs))
+    .route("/osdr/syncs/:id", get(routes::osdr::osdr_sync_report))
+    .route("/osdr/:dataset_id", get(routes::osdr::osdr_item))
     // Space cache
     .route("/space/:src/latest", get(routes::space_cache::space_latest))
     .route("/space/refresh", get(routes::space_cache::space_refresh))
     .route("/space/summary", get(routes::space_cache::space_summary))
+    .route("/space/sources", get(routes::space_cache::space_sources))
     // .layer(from_fn_with_state(state.clone(), rate_limit))
     .layer(from_fn_with_state(state.clone(), redis_noop))
     .with_state(state.clone());
@@ -182,4 +333,12 @@ async fn main() -> anyhow::Result<()> {
 
 fn env_u64(k: &str, d: u64) -> u64 {
     std::env::var(k).ok().and_then(|s| s.parse().ok()).unwrap_or(d)
-}
\ No newline at end of file
+}
+
+fn env_ids(k: &str, d: i32) -> Vec<i32> {
+    let ids: Vec<i32> = std::env::var(k).unwrap_or_default()
+        .split(',')
+        .filter_map(|s| s.trim().parse().ok())
+        .collect();
+    if ids.is_empty() { vec![d] } else { ids }
+}
@@ -1,14 +1,14 @@
 use axum::{
     extract::State,
-    http::Request,
     middleware::Next,
     response::Response,
     http::StatusCode,
 };
-use deadpool_redis::{Pool, Connection};
+use deadpool_redis::Connection;
 use deadpool_redis::redis::AsyncCommands; 
 use crate::app_state::AppState;
 
+#[allow(dead_code)] // подключается вручную в main.rs
 pub async fn rate_limit(
     State(st): State<AppState>,
     
@@ -19,9 +19,9 @@ pub async fn rate_limit(
 
     let mut conn: Connection = st.redis.get().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let count: i64 = conn.incr(&key, 1).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let count: i64 = conn.incr(key, 1).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     if count == 1 {
-        let _: () = conn.expire(&key, 60).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let _: () = conn.expire(key, 60).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     }
 
     if count > 60 {
@@ -0,0 +1,56 @@
+use axum::extract::{Query, State};
+use axum::{Json, http::StatusCode};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::Row;
+
+use crate::app_state::AppState;
+use crate::services::altitude_service::{daily_altitudes, decay_rate, find_reboosts, noise_km};
+use crate::services::iss_service::ISS_NORAD_ID;
+
+#[derive(Deserialize)]
+pub struct AltitudeQuery {
+    #[serde(default = "default_satellite")]
+    satellite: i32,
+    #[serde(default = "default_days")]
+    days: i64,
+}
+
+fn default_satellite() -> i32 { ISS_NORAD_ID }
+fn default_days() -> i64 { 60 }
+
+pub async fn altitude_summary(State(st): State<AppState>, Query(q): Query<AltitudeQuery>) -> Result<Json<Value>, (StatusCode, String)> {
+    let daily = daily_altitudes(&st.pool, q.satellite, q.days.clamp(1, 3650)).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let reboosts = find_reboosts(&daily);
+
+    Ok(Json(serde_json::json!({
+        "satellite_id": q.satellite,
+        "current_altitude_km": daily.last().map(|d| d.mean_km),
+        "decay_km_per_day": decay_rate(&daily, &reboosts),
+        "noise_km": noise_km(&daily),
+        "reboosts": reboosts,
+        "days": daily,
+    })))
+}
+
+pub async fn reboost_events(State(st): State<AppState>, Query(q): Query<AltitudeQuery>) -> Result<Json<Value>, (StatusCode, String)> {
+    let rows = sqlx::query(
+        "SELECT day, before_km, after_km, delta_km, detected_at FROM iss_reboost_events
+         WHERE satellite_id = $1 ORDER BY day DESC"
+    ).bind(q.satellite).fetch_all(&st.pool).await
+     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let out: Vec<Value> = rows.into_iter().map(|r| {
+        serde_json::json!({
+            "day": r.get::<NaiveDate,_>("day"),
+            "before_km": r.get::<f64,_>("before_km"),
+            "after_km": r.get::<f64,_>("after_km"),
+            "delta_km": r.get::<f64,_>("delta_km"),
+            "detected_at": r.get::<DateTime<Utc>,_>("detected_at"),
+        })
+    }).collect();
+
+    Ok(Json(serde_json::json!({ "satellite_id": q.satellite, "items": out })))
+}
@@ -1,41 +1,111 @@
-use axum::extract::State;
-use axum::{Json, http::StatusCode};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use axum::Json;
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::Row;
 
 use crate::app_state::AppState;
-use crate::services::iss_service::fetch_and_store_iss;
+use crate::services::cache_service::{cached, invalidate};
+use crate::services::iss_service::{fetch_and_store_iss, parse_position, ISS_NORAD_ID};
+use crate::utils::conditional::{respond, versioned};
+use crate::utils::geo::haversine_km;
+use crate::utils::geocode::lookup;
+use crate::utils::sun::{is_sunlit, DEFAULT_ALTITUDE_KM};
 
-pub async fn last_iss(State(st): State<AppState>) -> Result<Json<Value>, (StatusCode, String)> {
+#[derive(Deserialize)]
+pub struct SatelliteQuery {
+    #[serde(default = "default_satellite")]
+    satellite: i32,
+}
+
+#[derive(Deserialize)]
+pub struct TrendQuery {
+    #[serde(default = "default_satellite")]
+    satellite: i32,
+    #[serde(default)]
+    include_anomalies: bool,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    #[serde(default = "default_satellite")]
+    satellite: i32,
+    #[serde(default)]
+    include_anomalies: bool,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    #[serde(default = "default_history_limit")]
+    limit: i64,
+    /// Разрешение для свёрнутого (старого) участка: hour | day.
+    #[serde(default = "default_resolution")]
+    resolution: String,
+}
+
+#[derive(Deserialize)]
+pub struct PlacesQuery {
+    #[serde(default = "default_satellite")]
+    satellite: i32,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    /// country | land | sea | ocean; без фильтра — все.
+    kind: Option<String>,
+}
+
+fn default_satellite() -> i32 { ISS_NORAD_ID }
+fn default_history_limit() -> i64 { 500 }
+fn default_resolution() -> String { "hour".to_string() }
+
+pub async fn last_iss(State(st): State<AppState>, Query(q): Query<SatelliteQuery>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let v = cached(&st.redis, &format!("iss:last:{}", q.satellite), st.cache_ttl_last, load_last(&st, q.satellite)).await?;
+    Ok(respond(&headers, v))
+}
+
+async fn load_last(st: &AppState, satellite: i32) -> Result<Value, (StatusCode, String)> {
     let row_opt = sqlx::query(
-        "SELECT id, fetched_at, source_url, payload
+        "SELECT id, fetched_at, source_url, provider, COALESCE(normalized, payload) AS position, payload, satellite_id
          FROM iss_fetch_log
+         WHERE satellite_id = $1 AND origin = 'live'
          ORDER BY id DESC LIMIT 1"
-    ).fetch_optional(&st.pool).await
+    ).bind(satellite).fetch_optional(&st.pool).await
      .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     if let Some(row) = row_opt {
         let id: i64 = row.get("id");
         let fetched_at: DateTime<Utc> = row.get::<DateTime<Utc>, _>("fetched_at");
         let source_url: String = row.get("source_url");
-        let payload: Value = row.try_get("payload").unwrap_or(serde_json::json!({}));
-        return Ok(Json(serde_json::json!({
-            "id": id, "fetched_at": fetched_at, "source_url": source_url, "payload": payload
-        })));
+        let provider: Option<String> = row.get("provider");
+        let satellite_id: i32 = row.get("satellite_id");
+        let position: Value = row.try_get("position").unwrap_or(serde_json::json!({}));
+        let raw: Value = row.try_get("payload").unwrap_or(serde_json::json!({}));
+        let pos = parse_position(&position, fetched_at).ok();
+        let location = pos.as_ref().and_then(|p| lookup(p.lat, p.lon));
+        let sunlit = pos.as_ref()
+            .map(|p| is_sunlit(p.observed_at, p.lat, p.lon, p.altitude.unwrap_or(DEFAULT_ALTITUDE_KM)));
+        let body = serde_json::json!({
+            "id": id, "satellite_id": satellite_id, "fetched_at": fetched_at, "source_url": source_url, "provider": provider,
+            "location": location, "sunlit": sunlit, "payload": position, "raw": raw
+        });
+        return Ok(versioned(body, id.to_string(), Some(fetched_at)));
     }
-    Ok(Json(serde_json::json!({"message":"no data"})))
+    Ok(versioned(serde_json::json!({"message":"no data"}), "0".to_string(), None))
 }
 
-pub async fn trigger_iss(State(st): State<AppState>) -> Result<Json<Value>, (StatusCode, String)> {
-    fetch_and_store_iss(&st.pool, &st.fallback_url).await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    last_iss(State(st)).await
+pub async fn trigger_iss(State(st): State<AppState>, Query(q): Query<SatelliteQuery>) -> Result<Response, (StatusCode, String)> {
+    if !st.satellites.contains(&q.satellite) {
+        return Err((StatusCode::BAD_REQUEST, format!("satellite {} is not configured", q.satellite)));
+    }
+    fetch_and_store_iss(&st.pool, &st.iss_providers, q.satellite).await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+    invalidate(&st.redis, &["space:summary", &format!("iss:last:{}", q.satellite)]).await;
+    last_iss(State(st), Query(q), HeaderMap::new()).await
 }
 
 #[derive(Serialize)]
 pub struct Trend {
+    satellite_id: i32,
     movement: bool,
     delta_km: f64,
     dt_sec: f64,
@@ -46,63 +116,172 @@ pub struct Trend {
     from_lon: Option<f64>,
     to_lat: Option<f64>,
     to_lon: Option<f64>,
+    /// Освещена ли последняя точка (не в тени Земли).
+    sunlit: Option<bool>,
 }
 
-pub async fn iss_trend(State(st): State<AppState>) -> Result<Json<Trend>, (StatusCode, String)> {
-    let rows = sqlx::query("SELECT fetched_at, payload FROM iss_fetch_log ORDER BY id DESC LIMIT 2")
+pub async fn iss_trend(State(st): State<AppState>, Query(q): Query<TrendQuery>) -> Result<Json<Trend>, (StatusCode, String)> {
+    let rows = sqlx::query(
+        "SELECT observed_at, lat, lon, altitude, velocity FROM iss_positions
+         WHERE satellite_id = $1 AND ($2 OR anomaly IS NULL)
+         ORDER BY observed_at DESC, id DESC LIMIT 2"
+    )
+        .bind(q.satellite).bind(q.include_anomalies)
         .fetch_all(&st.pool).await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     if rows.len() < 2 {
         return Ok(Json(Trend {
+            satellite_id: q.satellite,
             movement: false, delta_km: 0.0, dt_sec: 0.0, velocity_kmh: None,
             from_time: None, to_time: None,
-            from_lat: None, from_lon: None, to_lat: None, to_lon: None
+            from_lat: None, from_lon: None, to_lat: None, to_lon: None, sunlit: None,
         }));
     }
 
-    let t2: DateTime<Utc> = rows[0].get("fetched_at");
-    let t1: DateTime<Utc> = rows[1].get("fetched_at");
-    let p2: Value = rows[0].get("payload");
-    let p1: Value = rows[1].get("payload");
-
-    let lat1 = num(&p1["latitude"]);
-    let lon1 = num(&p1["longitude"]);
-    let lat2 = num(&p2["latitude"]);
-    let lon2 = num(&p2["longitude"]);
-    let v2 = num(&p2["velocity"]);
-
-    let mut delta_km = 0.0;
-    let mut movement = false;
-    if let (Some(a1), Some(o1), Some(a2), Some(o2)) = (lat1, lon1, lat2, lon2) {
-        delta_km = haversine_km(a1, o1, a2, o2);
-        movement = delta_km > 0.1;
-    }
+    let t2: DateTime<Utc> = rows[0].get("observed_at");
+    let t1: DateTime<Utc> = rows[1].get("observed_at");
+    let (lat1, lon1): (f64, f64) = (rows[1].get("lat"), rows[1].get("lon"));
+    let (lat2, lon2): (f64, f64) = (rows[0].get("lat"), rows[0].get("lon"));
+    let v2: Option<f64> = rows[0].get("velocity");
+    let alt2: Option<f64> = rows[0].get("altitude");
+
+    let delta_km = haversine_km(lat1, lon1, lat2, lon2);
+    let movement = delta_km > 0.1;
     let dt_sec = (t2 - t1).num_milliseconds() as f64 / 1000.0;
 
     Ok(Json(Trend {
+        satellite_id: q.satellite,
         movement,
         delta_km,
         dt_sec,
         velocity_kmh: v2,
         from_time: Some(t1),
         to_time: Some(t2),
-        from_lat: lat1, from_lon: lon1, to_lat: lat2, to_lon: lon2,
+        from_lat: Some(lat1), from_lon: Some(lon1), to_lat: Some(lat2), to_lon: Some(lon2),
+        sunlit: Some(is_sunlit(t2, lat2, lon2, alt2.unwrap_or(DEFAULT_ALTITUDE_KM))),
     }))
 }
 
-fn num(v: &Value) -> Option<f64> {
-    if let Some(x) = v.as_f64() { return Some(x); }
-    if let Some(s) = v.as_str() { return s.parse::<f64>().ok(); }
-    None
+struct Sample {
+    at: DateTime<Utc>,
+    lat: f64,
+    lon: f64,
+    altitude: Option<f64>,
+    velocity: Option<f64>,
+    visibility: Option<String>,
+    anomaly: Option<String>,
+    origin: Option<String>,
+    resolution: String,
+}
+
+/// Сырые позиции плюс агрегаты за уже свёрнутый период (первая точка бакета).
+async fn load_history(st: &AppState, q: &HistoryQuery) -> Result<Vec<Sample>, (StatusCode, String)> {
+    let table = match q.resolution.as_str() {
+        "hour" => "iss_position_hourly",
+        "day" => "iss_position_daily",
+        other => return Err((StatusCode::BAD_REQUEST, format!("unknown resolution {other}, expected hour or day"))),
+    };
+    let sql = format!(
+        "SELECT * FROM (
+             SELECT observed_at, lat, lon, altitude, velocity, visibility, anomaly, origin, 'raw' AS resolution
+             FROM iss_positions
+             WHERE satellite_id = $1
+               AND ($2::timestamptz IS NULL OR observed_at >= $2)
+               AND ($3::timestamptz IS NULL OR observed_at <= $3)
+               AND ($5 OR anomaly IS NULL)
+             UNION ALL
+             SELECT first_at, first_lat, first_lon, avg_alt, avg_vel, NULL, NULL, NULL, $6
+             FROM {table}
+             WHERE satellite_id = $1
+               AND ($2::timestamptz IS NULL OR first_at >= $2)
+               AND ($3::timestamptz IS NULL OR first_at <= $3)
+             ORDER BY observed_at DESC LIMIT $4
+         ) t ORDER BY observed_at"
+    );
+    let rows = sqlx::query(&sql)
+    .bind(q.satellite).bind(q.from).bind(q.to).bind(q.limit.clamp(1, 5000)).bind(q.include_anomalies)
+    .bind(&q.resolution)
+    .fetch_all(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(rows.into_iter().map(|r| Sample {
+        at: r.get("observed_at"),
+        lat: r.get("lat"),
+        lon: r.get("lon"),
+        altitude: r.get("altitude"),
+        velocity: r.get("velocity"),
+        visibility: r.get("visibility"),
+        anomaly: r.get("anomaly"),
+        origin: r.get("origin"),
+        resolution: r.get("resolution"),
+    }).collect())
+}
+
+pub async fn iss_history(State(st): State<AppState>, Query(q): Query<HistoryQuery>) -> Result<Json<Value>, (StatusCode, String)> {
+    let items: Vec<Value> = load_history(&st, &q).await?.into_iter().map(|s| {
+        serde_json::json!({
+            "at": s.at, "lat": s.lat, "lon": s.lon,
+            "altitude": s.altitude, "velocity": s.velocity, "visibility": s.visibility,
+            "anomaly": s.anomaly, "origin": s.origin, "resolution": s.resolution, "location": lookup(s.lat, s.lon),
+            "sunlit": is_sunlit(s.at, s.lat, s.lon, s.altitude.unwrap_or(DEFAULT_ALTITUDE_KM)),
+        })
+    }).collect();
+    Ok(Json(serde_json::json!({ "satellite_id": q.satellite, "items": items })))
+}
+
+/// Тот же ряд, что и /iss/history, но в виде GeoJSON LineString для карты.
+pub async fn iss_track(State(st): State<AppState>, Query(q): Query<HistoryQuery>) -> Result<Json<Value>, (StatusCode, String)> {
+    let samples = load_history(&st, &q).await?;
+    let coords: Vec<Value> = samples.iter().map(|s| serde_json::json!([s.lon, s.lat])).collect();
+    Ok(Json(serde_json::json!({
+        "type": "Feature",
+        "properties": {
+            "satellite_id": q.satellite,
+            "from": samples.first().map(|s| s.at),
+            "to": samples.last().map(|s| s.at),
+        },
+        "geometry": { "type": "LineString", "coordinates": coords },
+    })))
 }
 
-fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-    let rlat1 = lat1.to_radians();
-    let rlat2 = lat2.to_radians();
-    let dlat = (lat2 - lat1).to_radians();
-    let dlon = (lon2 - lon1).to_radians();
-    let a = (dlat / 2.0).sin().powi(2) + rlat1.cos() * rlat2.cos() * (dlon / 2.0).sin().powi(2);
-    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-    6371.0 * c
-}
\ No newline at end of file
+/// Сколько времени спутник провёл над каждой страной/морем/океаном.
+/// Время точки — до следующей точки, но не больше двух интервалов опроса;
+/// последней точке (следующей нет) время не засчитывается.
+pub async fn iss_places(State(st): State<AppState>, Query(q): Query<PlacesQuery>) -> Result<Json<Value>, (StatusCode, String)> {
+    let rows = sqlx::query(
+        "WITH s AS (
+             SELECT place, place_iso, place_kind, observed_at,
+                    LEAD(observed_at) OVER (ORDER BY observed_at) AS next_at
+             FROM iss_positions
+             WHERE satellite_id = $1 AND anomaly IS NULL
+               AND ($2::timestamptz IS NULL OR observed_at >= $2)
+               AND ($3::timestamptz IS NULL OR observed_at <= $3)
+         )
+         SELECT place, place_iso, place_kind, COUNT(*) AS samples,
+                COALESCE(SUM(CASE WHEN next_at IS NULL THEN 0
+                             -- LEAST пропускает NULL, поэтому последняя точка проверяется явно
+                             ELSE LEAST(EXTRACT(EPOCH FROM next_at - observed_at)::float8, $4) END), 0) AS seconds
+         FROM s
+         WHERE place IS NOT NULL AND ($5::text IS NULL OR place_kind = $5)
+         GROUP BY place, place_iso, place_kind
+         ORDER BY seconds DESC, place"
+    )
+    .bind(q.satellite).bind(q.from).bind(q.to).bind((st.every_iss * 2) as f64).bind(&q.kind)
+    .fetch_all(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let total: f64 = rows.iter().map(|r| r.get::<f64, _>("seconds")).sum();
+    let items: Vec<Value> = rows.iter().map(|r| {
+        let seconds: f64 = r.get("seconds");
+        serde_json::json!({
+            "name": r.get::<String, _>("place"),
+            "iso": r.get::<Option<String>, _>("place_iso"),
+            "kind": r.get::<Option<String>, _>("place_kind"),
+            "samples": r.get::<i64, _>("samples"),
+            "seconds": seconds,
+            "share": if total > 0.0 { seconds / total } else { 0.0 },
+        })
+    }).collect();
+    Ok(Json(serde_json::json!({ "satellite_id": q.satellite, "total_seconds": total, "items": items })))
+}
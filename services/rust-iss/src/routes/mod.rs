@@ -1,4 +1,7 @@
+pub mod altitude;
 pub mod health;
 pub mod iss;
 pub mod osdr;
-pub mod space_cache;
\ No newline at end of file
+pub mod regions;
+pub mod space_cache;
+pub mod sun;
@@ -1,4 +1,7 @@
-use axum::extract::{Query, State};
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
 use axum::{Json, http::StatusCode};
 use chrono::{DateTime, Utc};
 use serde_json::Value;
@@ -6,7 +9,9 @@ use serde::Deserialize;
 use sqlx::Row;
 
 use crate::app_state::AppState;
-use crate::services::osdr_service::fetch_and_store_osdr;
+use crate::services::cache_service::cached;
+use crate::services::osdr_export::{raw_columns, spawn_export, ExportFormat, ExportQuery, MAX_RAW_COLUMNS};
+use crate::services::osdr_service::start_osdr_sync;
 
 
 #[derive(Deserialize)]
@@ -17,61 +22,536 @@ pub struct OsdrQuery {
     sort_by: String,  
     #[serde(default)]
     order: String,   
+    /// Показывать и датасеты, пропавшие из апстрима.
+    #[serde(default)]
+    include_removed: bool,
+    /// `next_cursor` из предыдущей страницы.
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct OsdrSearchQuery {
+    #[serde(default)]
+    q: String,
+    /// all | dataset_id | title | description
+    #[serde(default = "default_field")]
+    field: String,
+    status: Option<String>,
+    organism: Option<String>,
+    updated_after: Option<DateTime<Utc>>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
 }
 
+#[derive(Deserialize)]
+pub struct OsdrExportQuery {
+    /// csv | ndjson | parquet
+    #[serde(default = "default_format")]
+    format: String,
+    #[serde(default)]
+    sort_by: String,
+    #[serde(default)]
+    order: String,
+    #[serde(default)]
+    include_removed: bool,
+    /// Добавить поля raw колонками `raw.<путь>`.
+    #[serde(default)]
+    flatten_raw: bool,
+}
+
+#[derive(Deserialize)]
+pub struct OsdrSyncsQuery {
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+const MAX_LIST_LIMIT: i64 = 500;
+
 fn default_limit() -> i64 { 20 }
+fn default_field() -> String { "all".to_string() }
+fn default_format() -> String { "csv".to_string() }
 
-pub async fn osdr_sync(State(st): State<AppState>) -> Result<Json<Value>, (StatusCode, String)> {
-    let written = fetch_and_store_osdr(&st).await
+/// Синхронизация уходит в фон: 202 и id задания, прогресс — в `/osdr/sync/:job_id`.
+/// Пока одна синхронизация идёт, новые запросы получают её id.
+pub async fn osdr_sync(State(st): State<AppState>) -> Result<Response, (StatusCode, String)> {
+    let job = start_osdr_sync(&st).await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    Ok(Json(serde_json::json!({ "written": written })))
+    let status_url = format!("/osdr/sync/{}", job.id);
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, status_url.clone())],
+        Json(serde_json::json!({
+            "job_id": job.id,
+            "status": "running",
+            "coalesced": job.coalesced,
+            "status_url": status_url,
+        })),
+    ).into_response())
 }
 
+pub async fn osdr_sync_job(
+    State(st): State<AppState>,
+    Path(job_id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let row = sqlx::query(&format!("SELECT {SYNC_COLUMNS} FROM osdr_syncs WHERE id = $1"))
+        .bind(job_id)
+        .fetch_optional(&st.pool).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, format!("sync job {job_id} not found")))?;
+    Ok(Json(sync_json(&row)))
+}
 
 
+
+/// Курсорная пагинация: курсор — hex от `[значение сортировки, id, sort_by, order]`
+/// последней строки страницы; NULL-значения всегда идут в конце.
 pub async fn osdr_list(
     State(st): State<AppState>,
     Query(query): Query<OsdrQuery>,  
 ) -> Result<Json<Value>, (StatusCode, String)> {
-    
-    let valid_sort_columns = ["id", "dataset_id", "title", "status", "updated_at", "inserted_at"];
-    let sort_by = if valid_sort_columns.contains(&query.sort_by.as_str()) {
-        query.sort_by.clone()
-    } else {
-        "inserted_at".to_string()
-    };
+    let key = format!(
+        "osdr:list:{}:{}:{}:{}:{}",
+        query.limit, query.sort_by, query.order, query.include_removed, query.cursor.as_deref().unwrap_or("")
+    );
+    cached(&st.redis, &key, st.cache_ttl_osdr_list, load_list(&st, &query)).await.map(Json)
+}
 
-    let order = if query.order.to_lowercase() == "asc" {
-        "ASC"
-    } else {
-        "DESC"
+async fn load_list(st: &AppState, query: &OsdrQuery) -> Result<Value, (StatusCode, String)> {
+    let bad = |msg: String| (StatusCode::BAD_REQUEST, msg);
+
+    let ListOrder { sort_by, cast, order, cmp } = list_order(&query.sort_by, &query.order).map_err(bad)?;
+    if !(1..=MAX_LIST_LIMIT).contains(&query.limit) {
+        return Err(bad(format!("limit must be between 1 and {MAX_LIST_LIMIT}")));
+    }
+
+    let cursor = match &query.cursor {
+        Some(c) => {
+            let (value, id, c_sort, c_order) = decode_cursor(c).ok_or_else(|| bad("malformed cursor".to_string()))?;
+            if c_sort != sort_by || c_order != order {
+                return Err(bad("cursor was issued for a different sort_by/order".to_string()));
+            }
+            Some((value, id))
+        }
+        None => None,
     };
 
-    
+    // после строки с непустым ключом — дальше по ключу или все NULL; после NULL — только NULL дальше по id
     let query_str = format!(
-        "SELECT id, dataset_id, title, status, updated_at, inserted_at, raw
+        "SELECT id, dataset_id, title, status, description, organism, assay_types, mission,
+                release_date, upstream_updated_at, updated_at, inserted_at, removed_at, raw,
+                {sort_by}::text AS cursor_value
          FROM osdr_items
-         ORDER BY {} {}
-         LIMIT $1",
-        sort_by, order
+         WHERE ($2 OR removed_at IS NULL)
+           AND ($3 = false
+                OR ($4::text IS NOT NULL AND (({sort_by} IS NOT NULL AND ({sort_by}, id) {cmp} ($4::{cast}, $5)) OR {sort_by} IS NULL))
+                OR ($4::text IS NULL AND {sort_by} IS NULL AND id {cmp} $5))
+         ORDER BY ({sort_by} IS NULL), {sort_by} {order}, id {order}
+         LIMIT $1"
     );
 
-    let rows = sqlx::query(&query_str)  
-        .bind(query.limit)  
+    let (c_value, c_id) = cursor.clone().unwrap_or((None, 0));
+    let mut rows = sqlx::query(&query_str)  
+        .bind(query.limit + 1)  
+        .bind(query.include_removed)
+        .bind(cursor.is_some())
+        .bind(c_value)
+        .bind(c_id)
         .fetch_all(&st.pool).await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    let total: i64 = sqlx::query("SELECT count(*) AS c FROM osdr_items WHERE $1 OR removed_at IS NULL")
+        .bind(query.include_removed)
+        .fetch_one(&st.pool).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .get("c");
+
+    let has_more = rows.len() as i64 > query.limit;
+    rows.truncate(query.limit as usize);
+    let next_cursor = rows.last().filter(|_| has_more).map(|r| {
+        encode_cursor(r.get::<Option<String>, _>("cursor_value"), r.get("id"), sort_by, order)
+    });
+
     let out: Vec<Value> = rows.into_iter().map(|r| {
         serde_json::json!({
             "id": r.get::<i64,_>("id"),
             "dataset_id": r.get::<Option<String>,_>("dataset_id"),
             "title": r.get::<Option<String>,_>("title"),
             "status": r.get::<Option<String>,_>("status"),
+            "description": r.get::<Option<String>,_>("description"),
+            "organism": r.get::<Option<String>,_>("organism"),
+            "assay_types": r.get::<Option<Vec<String>>,_>("assay_types"),
+            "mission": r.get::<Option<String>,_>("mission"),
+            "release_date": r.get::<Option<DateTime<Utc>>,_>("release_date"),
+            "upstream_updated_at": r.get::<Option<DateTime<Utc>>,_>("upstream_updated_at"),
             "updated_at": r.get::<Option<DateTime<Utc>>,_>("updated_at"),
             "inserted_at": r.get::<DateTime<Utc>, _>("inserted_at"),
+            "removed_at": r.get::<Option<DateTime<Utc>>,_>("removed_at"),
             "raw": r.get::<Value,_>("raw"),
         })
     }).collect();
 
-    Ok(Json(serde_json::json!({ "items": out })))
-}
\ No newline at end of file
+    Ok(serde_json::json!({ "items": out, "total": total, "limit": query.limit, "next_cursor": next_cursor }))
+}
+
+struct ListOrder {
+    sort_by: &'static str,
+    cast: &'static str,
+    order: &'static str,
+    cmp: &'static str,
+}
+
+/// Проверка sort_by/order — общая для списка и выгрузки.
+fn list_order(sort_by: &str, order: &str) -> Result<ListOrder, String> {
+    let (sort_by, cast) = match sort_by {
+        "id" => ("id", "bigint"),
+        "dataset_id" => ("dataset_id", "text"),
+        "title" => ("title", "text"),
+        "status" => ("status", "text"),
+        "updated_at" => ("updated_at", "timestamptz"),
+        "inserted_at" | "" => ("inserted_at", "timestamptz"),
+        "release_date" => ("release_date", "timestamptz"),
+        "upstream_updated_at" => ("upstream_updated_at", "timestamptz"),
+        other => return Err(format!(
+            "unknown sort_by {other}, expected one of id, dataset_id, title, status, updated_at, inserted_at, release_date, upstream_updated_at"
+        )),
+    };
+    let (order, cmp) = match order.to_lowercase().as_str() {
+        "asc" => ("ASC", ">"),
+        "desc" | "" => ("DESC", "<"),
+        other => return Err(format!("unknown order {other}, expected asc or desc")),
+    };
+    Ok(ListOrder { sort_by, cast, order, cmp })
+}
+
+fn encode_cursor(value: Option<String>, id: i64, sort_by: &str, order: &str) -> String {
+    let json = serde_json::json!([value, id, sort_by, order]).to_string();
+    json.bytes().map(|b| format!("{b:02x}")).collect()
+}
+
+fn decode_cursor(cursor: &str) -> Option<(Option<String>, i64, String, String)> {
+    let bytes = (0..cursor.len()).step_by(2)
+        .map(|i| cursor.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()))
+        .collect::<Option<Vec<u8>>>()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+// Фильтры поиска, общие для /osdr/search и /osdr/facets:
+// $1 запрос, $2 веса tsvector, $3 status, $4 organism, $5 updated_after, $6 поиск по dataset_id.
+// Подстроки ищутся через strpos, а не ILIKE: `%` и `_` из запроса — обычные символы.
+const SEARCH_FILTER: &str = "removed_at IS NULL
+           AND ($1::text IS NULL OR CASE WHEN $6 THEN strpos(lower(dataset_id), lower($1)) > 0
+                                         ELSE search_tsv @@ tsq AND ($2::text IS NULL OR ts_filter(search_tsv, $2::text::\"char\"[]) @@ tsq) END)
+           AND ($3::text IS NULL OR status = $3)
+           AND ($4::text IS NULL OR strpos(lower(organism), lower($4)) > 0)
+           AND ($5::timestamptz IS NULL OR coalesce(upstream_updated_at, updated_at) >= $5)";
+
+// id — не текст, по нему ищем подстрокой
+fn search_weights(field: &str) -> Result<Option<&'static str>, (StatusCode, String)> {
+    match field {
+        "all" | "dataset_id" => Ok(None),
+        "title" => Ok(Some("{b}")),
+        "description" => Ok(Some("{c}")),
+        other => Err((StatusCode::BAD_REQUEST, format!("unknown field {other}, expected all, dataset_id, title or description"))),
+    }
+}
+
+/// Поиск по всему каталогу: websearch-синтаксис Postgres, ранжирование
+/// по весам (id > заголовок > описание) и сниппеты с подсветкой `<mark>`.
+pub async fn osdr_search(
+    State(st): State<AppState>,
+    Query(q): Query<OsdrSearchQuery>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let weights = search_weights(&q.field)?;
+    let text = Some(q.q.trim()).filter(|s| !s.is_empty());
+
+    let rows = sqlx::query(&format!(
+        "SELECT i.id, dataset_id, title, status, organism, assay_types, mission,
+                release_date, upstream_updated_at, updated_at,
+                CASE WHEN $1::text IS NULL THEN 0 ELSE ts_rank_cd(search_tsv, tsq) END AS rank,
+                CASE WHEN $1::text IS NULL THEN NULL
+                     ELSE ts_headline('english', coalesce(title, dataset_id, ''), tsq,
+                                      'StartSel=<mark>,StopSel=</mark>,HighlightAll=true') END AS title_snippet,
+                CASE WHEN $1::text IS NULL OR description IS NULL THEN NULL
+                     ELSE ts_headline('english', description, tsq,
+                                      'StartSel=<mark>,StopSel=</mark>,MaxFragments=2,MaxWords=30,MinWords=10') END AS snippet,
+                count(*) OVER () AS total
+         FROM osdr_items i, websearch_to_tsquery('english', coalesce($1, '')) AS tsq
+         WHERE {SEARCH_FILTER}
+         ORDER BY rank DESC, coalesce(upstream_updated_at, updated_at) DESC NULLS LAST, i.id
+         LIMIT $7 OFFSET $8"
+    ))
+    .bind(text).bind(weights).bind(&q.status).bind(&q.organism).bind(q.updated_after).bind(q.field == "dataset_id")
+    .bind(q.limit.clamp(1, 100)).bind(q.offset.max(0))
+    .fetch_all(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let total = rows.first().map(|r| r.get::<i64, _>("total")).unwrap_or(0);
+    let out: Vec<Value> = rows.into_iter().map(|r| {
+        serde_json::json!({
+            "id": r.get::<i64,_>("id"),
+            "dataset_id": r.get::<Option<String>,_>("dataset_id"),
+            "title": r.get::<Option<String>,_>("title"),
+            "status": r.get::<Option<String>,_>("status"),
+            "organism": r.get::<Option<String>,_>("organism"),
+            "assay_types": r.get::<Option<Vec<String>>,_>("assay_types"),
+            "mission": r.get::<Option<String>,_>("mission"),
+            "release_date": r.get::<Option<DateTime<Utc>>,_>("release_date"),
+            "upstream_updated_at": r.get::<Option<DateTime<Utc>>,_>("upstream_updated_at"),
+            "updated_at": r.get::<Option<DateTime<Utc>>,_>("updated_at"),
+            "rank": r.get::<f32,_>("rank"),
+            "title_snippet": r.get::<Option<String>,_>("title_snippet"),
+            "snippet": r.get::<Option<String>,_>("snippet"),
+        })
+    }).collect();
+
+    Ok(Json(serde_json::json!({ "total": total, "items": out })))
+}
+
+/// Карточка датасета: нормализованные поля, raw, метаданные и история изменений.
+pub async fn osdr_item(
+    State(st): State<AppState>,
+    Path(dataset_id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let row = sqlx::query(
+        "SELECT id, dataset_id, title, status, description, organism, assay_types, mission,
+                release_date, upstream_updated_at, updated_at, inserted_at, removed_at, enriched_at,
+                enrich_error, enrich_attempted_at, raw, metadata
+         FROM osdr_items WHERE dataset_id = $1"
+    )
+    .bind(&dataset_id)
+    .fetch_optional(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, format!("dataset {dataset_id} not found")))?;
+
+    let history = sqlx::query(
+        "SELECT change, changed_at, diff FROM osdr_item_history
+         WHERE dataset_id = $1 ORDER BY changed_at DESC, id DESC LIMIT 200"
+    )
+    .bind(&dataset_id)
+    .fetch_all(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let history: Vec<Value> = history.into_iter().map(|h| {
+        serde_json::json!({
+            "change": h.get::<String,_>("change"),
+            "changed_at": h.get::<DateTime<Utc>,_>("changed_at"),
+            "diff": h.get::<Option<Value>,_>("diff"),
+        })
+    }).collect();
+
+    Ok(Json(serde_json::json!({
+        "id": row.get::<i64,_>("id"),
+        "dataset_id": row.get::<Option<String>,_>("dataset_id"),
+        "title": row.get::<Option<String>,_>("title"),
+        "status": row.get::<Option<String>,_>("status"),
+        "description": row.get::<Option<String>,_>("description"),
+        "organism": row.get::<Option<String>,_>("organism"),
+        "assay_types": row.get::<Option<Vec<String>>,_>("assay_types"),
+        "mission": row.get::<Option<String>,_>("mission"),
+        "release_date": row.get::<Option<DateTime<Utc>>,_>("release_date"),
+        "upstream_updated_at": row.get::<Option<DateTime<Utc>>,_>("upstream_updated_at"),
+        "updated_at": row.get::<Option<DateTime<Utc>>,_>("updated_at"),
+        "inserted_at": row.get::<DateTime<Utc>,_>("inserted_at"),
+        "removed_at": row.get::<Option<DateTime<Utc>>,_>("removed_at"),
+        "enriched_at": row.get::<Option<DateTime<Utc>>,_>("enriched_at"),
+        "enrich_error": row.get::<Option<String>,_>("enrich_error"),
+        "enrich_attempted_at": row.get::<Option<DateTime<Utc>>,_>("enrich_attempted_at"),
+        "raw": row.get::<Value,_>("raw"),
+        "metadata": row.get::<Option<Value>,_>("metadata"),
+        "history": history,
+    })))
+}
+
+/// Счётчики для фильтров: статус, организм, тип эксперимента, миссия и год
+/// релиза по тем же условиям, что и `/osdr/search` (limit/offset не действуют).
+pub async fn osdr_facets(
+    State(st): State<AppState>,
+    Query(q): Query<OsdrSearchQuery>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let weights = search_weights(&q.field)?;
+    let text = Some(q.q.trim()).filter(|s| !s.is_empty());
+
+    let rows = sqlx::query(&format!(
+        "WITH f AS (
+             SELECT status, organism, assay_types, mission, release_date
+             FROM osdr_items i, websearch_to_tsquery('english', coalesce($1, '')) AS tsq
+             WHERE {SEARCH_FILTER}
+         )
+         SELECT 'status' AS facet, status AS value, count(*) AS n FROM f GROUP BY 2
+         UNION ALL SELECT 'organism', organism, count(*) FROM f GROUP BY 2
+         UNION ALL SELECT 'assay_type', a, count(*) FROM f, unnest(assay_types) AS a GROUP BY 2
+         UNION ALL SELECT 'mission', mission, count(*) FROM f GROUP BY 2
+         UNION ALL SELECT 'release_year', extract(year FROM release_date)::int::text, count(*) FROM f GROUP BY 2
+         UNION ALL SELECT 'total', NULL, count(*) FROM f
+         ORDER BY facet, n DESC, value NULLS LAST"
+    ))
+    .bind(text).bind(weights).bind(&q.status).bind(&q.organism).bind(q.updated_after).bind(q.field == "dataset_id")
+    .fetch_all(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut total = 0;
+    let mut facets = serde_json::Map::new();
+    for name in ["status", "organism", "assay_type", "mission", "release_year"] {
+        facets.insert(name.to_string(), Value::Array(Vec::new()));
+    }
+    for r in rows {
+        let facet: String = r.get("facet");
+        let n: i64 = r.get("n");
+        let value: Option<String> = r.get("value");
+        if facet == "total" {
+            total = n;
+            continue;
+        }
+        // год отдаём числом
+        let value = match (facet.as_str(), value) {
+            ("release_year", Some(y)) => y.parse::<i64>().map_or(Value::Null, Value::from),
+            (_, v) => v.map_or(Value::Null, Value::from),
+        };
+        if let Some(Value::Array(list)) = facets.get_mut(&facet) {
+            list.push(serde_json::json!({ "value": value, "count": n }));
+        }
+    }
+
+    Ok(Json(serde_json::json!({ "total": total, "facets": facets })))
+}
+
+/// Весь каталог потоком, с фильтрами и сортировкой как у `/osdr/list`.
+pub async fn osdr_export(
+    State(st): State<AppState>,
+    Query(q): Query<OsdrExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let bad = |msg: String| (StatusCode::BAD_REQUEST, msg);
+    let format = ExportFormat::parse(&q.format)
+        .ok_or_else(|| bad(format!("unknown format {}, expected csv, ndjson or parquet", q.format)))?;
+    let ListOrder { sort_by, order, .. } = list_order(&q.sort_by, &q.order).map_err(bad)?;
+
+    let raw_columns = if q.flatten_raw {
+        let cols = raw_columns(&st.pool, q.include_removed).await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if cols.len() > MAX_RAW_COLUMNS {
+            return Err(bad(format!("raw has more than {MAX_RAW_COLUMNS} distinct fields, export without flatten_raw")));
+        }
+        cols
+    } else {
+        Vec::new()
+    };
+
+    let rx = spawn_export(st.pool.clone(), format, ExportQuery {
+        include_removed: q.include_removed,
+        order_by: format!("({sort_by} IS NULL), {sort_by} {order}, id {order}"),
+        raw_columns,
+    });
+    let chunks = futures_util::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|c| (c, rx)) });
+    let filename = format!("attachment; filename=\"osdr_items.{}\"", format.extension());
+    Ok((
+        [(header::CONTENT_TYPE, format.content_type().to_string()), (header::CONTENT_DISPOSITION, filename)],
+        Body::from_stream(chunks),
+    ).into_response())
+}
+
+/// Последние прогоны синхронизации: только счётчики, списки id — в `/osdr/syncs/:id`.
+pub async fn osdr_syncs(
+    State(st): State<AppState>,
+    Query(q): Query<OsdrSyncsQuery>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let rows = sqlx::query(&format!("SELECT {SYNC_COLUMNS} FROM osdr_syncs ORDER BY id DESC LIMIT $1"))
+    .bind(q.limit.clamp(1, 200))
+    .fetch_all(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let items: Vec<Value> = rows.iter().map(sync_json).collect();
+    Ok(Json(serde_json::json!({ "items": items })))
+}
+
+pub async fn osdr_sync_report(
+    State(st): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let row = sqlx::query(&format!("SELECT {SYNC_COLUMNS}, added_ids, modified_ids, removed_ids FROM osdr_syncs WHERE id = $1"))
+    .bind(id)
+    .fetch_optional(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, format!("sync {id} not found")))?;
+
+    let mut out = sync_json(&row);
+    out["added_ids"] = serde_json::json!(row.get::<Vec<String>,_>("added_ids"));
+    out["modified_ids"] = serde_json::json!(row.get::<Vec<String>,_>("modified_ids"));
+    out["removed_ids"] = serde_json::json!(row.get::<Vec<String>,_>("removed_ids"));
+    Ok(Json(out))
+}
+
+const SYNC_COLUMNS: &str = "id, started_at, finished_at, heartbeat_at, status, phase, processed, expected, error, source_url,
+    total, added, modified, removed, unchanged, load_ms, rows_per_sec";
+
+fn sync_json(r: &sqlx::postgres::PgRow) -> Value {
+    let started: DateTime<Utc> = r.get("started_at");
+    let finished: Option<DateTime<Utc>> = r.get("finished_at");
+    serde_json::json!({
+        "id": r.get::<i64,_>("id"),
+        "started_at": started,
+        "finished_at": finished,
+        "duration_ms": finished.map(|f| (f - started).num_milliseconds()),
+        "heartbeat_at": r.get::<DateTime<Utc>,_>("heartbeat_at"),
+        "status": r.get::<String,_>("status"),
+        "phase": r.get::<Option<String>,_>("phase"),
+        "progress": {
+            "processed": r.get::<i32,_>("processed"),
+            "total": r.get::<Option<i32>,_>("expected"),
+        },
+        "error": r.get::<Option<String>,_>("error"),
+        "source_url": r.get::<String,_>("source_url"),
+        "total": r.get::<i32,_>("total"),
+        "added": r.get::<i32,_>("added"),
+        "modified": r.get::<i32,_>("modified"),
+        "removed": r.get::<i32,_>("removed"),
+        "unchanged": r.get::<i32,_>("unchanged"),
+        "load_ms": r.get::<Option<i64>,_>("load_ms"),
+        "rows_per_sec": r.get::<Option<f64>,_>("rows_per_sec"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trip() {
+        let c = encode_cursor(Some("2024-01-02T03:04:05Z".into()), 42, "updated_at", "DESC");
+        assert!(c.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(decode_cursor(&c), Some((Some("2024-01-02T03:04:05Z".into()), 42, "updated_at".into(), "DESC".into())));
+        // NULL в колонке сортировки и не-ASCII значения
+        let c = encode_cursor(None, 7, "title", "ASC");
+        assert_eq!(decode_cursor(&c), Some((None, 7, "title".into(), "ASC".into())));
+        let c = encode_cursor(Some("Мышь \"/\"".into()), 1, "title", "ASC");
+        assert_eq!(decode_cursor(&c).unwrap().0.as_deref(), Some("Мышь \"/\""));
+    }
+
+    #[test]
+    fn known_cursor_encoding() {
+        // [null,1,"id","ASC"] в hex
+        assert_eq!(encode_cursor(None, 1, "id", "ASC"), "5b6e756c6c2c312c226964222c22415343225d");
+    }
+
+    #[test]
+    fn rejects_garbage_cursors() {
+        assert_eq!(decode_cursor("zz"), None);
+        assert_eq!(decode_cursor("5b6"), None); // нечётная длина
+        assert_eq!(decode_cursor(""), None);
+        // валидный hex, но не тот JSON
+        assert_eq!(decode_cursor(&"{\"a\":1}".bytes().map(|b| format!("{b:02x}")).collect::<String>()), None);
+        let c = encode_cursor(Some("x".into()), 1, "id", "ASC");
+        assert_eq!(decode_cursor(&c[..c.len() - 2]), None);
+    }
+
+    #[test]
+    fn validates_sort_and_order() {
+        let o = list_order("", "").unwrap();
+        assert_eq!((o.sort_by, o.cast, o.order, o.cmp), ("inserted_at", "timestamptz", "DESC", "<"));
+        let o = list_order("id", "ASC").unwrap();
+        assert_eq!((o.sort_by, o.cast, o.order, o.cmp), ("id", "bigint", "ASC", ">"));
+        assert!(list_order("raw", "asc").is_err());
+        assert!(list_order("id", "up").is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use axum::extract::{Path, Query, State};
+use axum::{Json, http::StatusCode};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::Row;
+
+use crate::app_state::AppState;
+use crate::utils::geo::parse_polygons;
+
+#[derive(Deserialize)]
+pub struct NewRegion {
+    name: String,
+    geometry: Value,
+}
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 { 100 }
+
+pub async fn list_regions(State(st): State<AppState>) -> Result<Json<Value>, (StatusCode, String)> {
+    let rows = sqlx::query("SELECT id, name, geometry, inside, created_at FROM iss_regions ORDER BY id")
+        .fetch_all(&st.pool).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let out: Vec<Value> = rows.into_iter().map(|r| {
+        serde_json::json!({
+            "id": r.get::<i64,_>("id"),
+            "name": r.get::<String,_>("name"),
+            "geometry": r.get::<Value,_>("geometry"),
+            "inside": r.get::<bool,_>("inside"),
+            "created_at": r.get::<DateTime<Utc>,_>("created_at"),
+        })
+    }).collect();
+
+    Ok(Json(serde_json::json!({ "items": out })))
+}
+
+pub async fn create_region(
+    State(st): State<AppState>,
+    Json(body): Json<NewRegion>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, String)> {
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "name is required".to_string()));
+    }
+    parse_polygons(&body.geometry).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let row = sqlx::query(
+        "INSERT INTO iss_regions(name, geometry) VALUES($1,$2) RETURNING id, created_at"
+    )
+    .bind(name).bind(&body.geometry)
+    .fetch_one(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(serde_json::json!({
+        "id": row.get::<i64,_>("id"),
+        "name": name,
+        "geometry": body.geometry,
+        "inside": false,
+        "created_at": row.get::<DateTime<Utc>,_>("created_at"),
+    }))))
+}
+
+pub async fn delete_region(Path(id): Path<i64>, State(st): State<AppState>) -> Result<StatusCode, (StatusCode, String)> {
+    let res = sqlx::query("DELETE FROM iss_regions WHERE id = $1")
+        .bind(id).execute(&st.pool).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if res.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, format!("region {id} not found")));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn region_events(
+    Path(id): Path<i64>,
+    State(st): State<AppState>,
+    Query(q): Query<EventsQuery>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let region = sqlx::query("SELECT name, inside FROM iss_regions WHERE id = $1")
+        .bind(id).fetch_optional(&st.pool).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, format!("region {id} not found")))?;
+
+    let rows = sqlx::query(
+        "SELECT id, fetch_id, event, occurred_at, lat, lon
+         FROM iss_region_events
+         WHERE region_id = $1
+         ORDER BY occurred_at DESC, id DESC
+         LIMIT $2"
+    )
+    .bind(id).bind(q.limit.clamp(1, 1000))
+    .fetch_all(&st.pool).await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let out: Vec<Value> = rows.into_iter().map(|r| {
+        serde_json::json!({
+            "id": r.get::<i64,_>("id"),
+            "fetch_id": r.get::<Option<i64>,_>("fetch_id"),
+            "event": r.get::<String,_>("event"),
+            "occurred_at": r.get::<DateTime<Utc>,_>("occurred_at"),
+            "lat": r.get::<f64,_>("lat"),
+            "lon": r.get::<f64,_>("lon"),
+        })
+    }).collect();
+
+    Ok(Json(serde_json::json!({
+        "region_id": id,
+        "name": region.get::<String,_>("name"),
+        "inside": region.get::<bool,_>("inside"),
+        "items": out,
+    })))
+}
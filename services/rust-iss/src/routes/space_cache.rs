@@ -1,28 +1,85 @@
 use axum::extract::{Path, Query, State};
-use axum::{Json, http::StatusCode};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use axum::Json;
 use chrono::{DateTime, Utc};
 use serde_json::Value;
 use std::collections::HashMap;
 use sqlx::Row;
 
 use crate::app_state::AppState;
+use crate::services::cache_service::cached;
+use crate::services::iss_service::ISS_NORAD_ID;
 use crate::services::space_cache_service::{
-    fetch_apod, fetch_neo_feed, fetch_donki_flr, fetch_donki_cme, fetch_spacex_next
+    find_source, fetch_apod, fetch_neo_feed, fetch_donki_flr, fetch_donki_cme, fetch_spacex_next, SPACE_SOURCES
 };
+use crate::utils::conditional::{respond, versioned};
 
-pub async fn space_latest(Path(src): Path<String>, State(st): State<AppState>) -> Result<Json<Value>, (StatusCode, String)> {
+pub async fn space_latest(Path(src): Path<String>, State(st): State<AppState>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let Some(source) = find_source(&src) else {
+        let known: Vec<&str> = SPACE_SOURCES.iter().map(|s| s.name).collect();
+        return Err((StatusCode::NOT_FOUND, format!("unknown source {src}; known: {}", known.join(", "))));
+    };
+    let mut v = cached(&st.redis, &format!("space:latest:{src}"), st.cache_ttl_latest, load_latest(&st, &src)).await?;
+
+    // возраст считается на каждый запрос, поэтому не кэшируется, а ETag слабый;
+    // stale в нём есть, чтобы клиент не получил 304 на ставшую устаревшей запись
+    let fetched_at = v.pointer("/body/fetched_at").and_then(|t| serde_json::from_value(t.clone()).ok());
+    let (age_seconds, stale) = source.freshness(&st, fetched_at);
+    if let Some(body) = v.get_mut("body").and_then(Value::as_object_mut) {
+        body.insert("age_seconds".to_string(), age_seconds.into());
+        body.insert("stale".to_string(), stale.into());
+    }
+    if let Some(etag) = v.get("etag").and_then(Value::as_str) {
+        v["etag"] = format!("{etag}-{stale}").into();
+    }
+    // то же для If-Modified-Since: представление поменялось, когда запись устарела
+    if let (true, Some(t)) = (stale, fetched_at) {
+        v["last_modified"] = serde_json::json!(source.stale_at(&st, t));
+    }
+    Ok(respond(&headers, v))
+}
+
+/// Известные источники: описание, период опроса, последняя загрузка, число строк.
+pub async fn space_sources(State(st): State<AppState>) -> Result<Json<Value>, (StatusCode, String)> {
+    let names: Vec<&str> = SPACE_SOURCES.iter().map(|s| s.name).collect();
+    let rows = sqlx::query(
+        "SELECT source, count(*) AS rows, max(fetched_at) AS last_fetched_at
+         FROM space_cache WHERE source = ANY($1) GROUP BY source"
+    ).bind(&names).fetch_all(&st.pool).await
+     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let out: Vec<Value> = SPACE_SOURCES.iter().map(|s| {
+        let row = rows.iter().find(|r| r.get::<String, _>("source") == s.name);
+        let last_fetched_at: Option<DateTime<Utc>> = row.and_then(|r| r.get("last_fetched_at"));
+        let (age_seconds, stale) = s.freshness(&st, last_fetched_at);
+        serde_json::json!({
+            "name": s.name,
+            "description": s.description,
+            "interval_seconds": s.interval(&st),
+            "last_fetched_at": last_fetched_at,
+            "rows": row.map_or(0, |r| r.get::<i64, _>("rows")),
+            "age_seconds": age_seconds,
+            "stale": stale,
+        })
+    }).collect();
+    Ok(Json(serde_json::json!({ "sources": out })))
+}
+
+async fn load_latest(st: &AppState, src: &str) -> Result<Value, (StatusCode, String)> {
     let row = sqlx::query(
-        "SELECT fetched_at, payload FROM space_cache
+        "SELECT id, fetched_at, payload FROM space_cache
          WHERE source = $1 ORDER BY id DESC LIMIT 1"
-    ).bind(&src).fetch_optional(&st.pool).await
+    ).bind(src).fetch_optional(&st.pool).await
      .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     if let Some(r) = row {
         let fetched_at: DateTime<Utc> = r.get("fetched_at");
         let payload: Value = r.get("payload");
-        return Ok(Json(serde_json::json!({ "source": src, "fetched_at": fetched_at, "payload": payload })));
+        let body = serde_json::json!({ "source": src, "fetched_at": fetched_at, "payload": payload });
+        return Ok(versioned(body, format!("W/{}", r.get::<i64, _>("id")), Some(fetched_at)));
     }
-    Ok(Json(serde_json::json!({ "source": src, "message":"no data" })))
+    Ok(versioned(serde_json::json!({ "source": src, "message":"no data" }), "W/0".to_string(), None))
 }
 
 pub async fn space_refresh(Query(q): Query<HashMap<String,String>>, State(st): State<AppState>) -> Result<Json<Value>, (StatusCode, String)> {
@@ -41,31 +98,43 @@ pub async fn space_refresh(Query(q): Query<HashMap<String,String>>, State(st): S
     Ok(Json(serde_json::json!({ "refreshed": done })))
 }
 
-async fn latest_from_cache(pool: &sqlx::PgPool, src: &str) -> Value {
-    sqlx::query("SELECT fetched_at, payload FROM space_cache WHERE source=$1 ORDER BY id DESC LIMIT 1")
-        .bind(src)
-        .fetch_optional(pool).await.ok().flatten()
-        .map(|r| serde_json::json!({"at": r.get::<DateTime<Utc>,_>("fetched_at"), "payload": r.get::<Value,_>("payload")}))
-        .unwrap_or(serde_json::json!({}))
+pub async fn space_summary(State(st): State<AppState>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let v = cached(&st.redis, "space:summary", st.cache_ttl_summary, load_summary(&st)).await?;
+    Ok(respond(&headers, v))
 }
 
-pub async fn space_summary(State(st): State<AppState>) -> Result<Json<Value>, (StatusCode, String)> {
-    let apod   = latest_from_cache(&st.pool, "apod").await;
-    let neo    = latest_from_cache(&st.pool, "neo").await;
-    let flr    = latest_from_cache(&st.pool, "flr").await;
-    let cme    = latest_from_cache(&st.pool, "cme").await;
-    let spacex = latest_from_cache(&st.pool, "spacex").await;
+/// Три запроса параллельно: последние записи всех источников одним DISTINCT ON,
+/// последняя позиция ISS и число датасетов. ETag — из id этих записей.
+async fn load_summary(st: &AppState) -> Result<Value, (StatusCode, String)> {
+    let err = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let (latest, iss, osdr) = tokio::try_join!(
+        sqlx::query(
+            "SELECT DISTINCT ON (source) id, source, fetched_at, payload
+             FROM space_cache WHERE source = ANY($1)
+             ORDER BY source, id DESC"
+        ).bind(SPACE_SOURCES.iter().map(|s| s.name).collect::<Vec<_>>()).fetch_all(&st.pool),
+        sqlx::query("SELECT id, fetched_at, COALESCE(normalized, payload) AS payload FROM iss_fetch_log WHERE satellite_id=$1 AND origin='live' ORDER BY id DESC LIMIT 1")
+            .bind(ISS_NORAD_ID).fetch_optional(&st.pool),
+        sqlx::query("SELECT count(*) AS c FROM osdr_items WHERE removed_at IS NULL").fetch_one(&st.pool),
+    ).map_err(err)?;
 
-    let iss_last = sqlx::query("SELECT fetched_at,payload FROM iss_fetch_log ORDER BY id DESC LIMIT 1")
-        .fetch_optional(&st.pool).await.ok().flatten()
-        .map(|r| serde_json::json!({"at": r.get::<DateTime<Utc>,_>("fetched_at"), "payload": r.get::<Value,_>("payload")}))
-        .unwrap_or(serde_json::json!({}));
-
-    let osdr_count: i64 = sqlx::query("SELECT count(*) AS c FROM osdr_items")
-        .fetch_one(&st.pool).await.map(|r| r.get::<i64,_>("c")).unwrap_or(0);
+    let entry = |r: &sqlx::postgres::PgRow| serde_json::json!({"at": r.get::<DateTime<Utc>,_>("fetched_at"), "payload": r.get::<Value,_>("payload")});
+    let mut body = serde_json::Map::new();
+    let mut ids = Vec::new();
+    let mut last_modified: Option<DateTime<Utc>> = None;
+    for src in SPACE_SOURCES.iter().map(|s| s.name) {
+        let row = latest.iter().find(|r| r.get::<String,_>("source") == src);
+        body.insert(src.to_string(), row.map(entry).unwrap_or(serde_json::json!({})));
+        ids.push(row.map_or(0, |r| r.get::<i64,_>("id")));
+        last_modified = last_modified.max(row.map(|r| r.get("fetched_at")));
+    }
+    body.insert("iss".to_string(), iss.as_ref().map(entry).unwrap_or(serde_json::json!({})));
+    ids.push(iss.as_ref().map_or(0, |r| r.get::<i64,_>("id")));
+    last_modified = last_modified.max(iss.as_ref().map(|r| r.get("fetched_at")));
+    let osdr_count: i64 = osdr.get("c");
+    body.insert("osdr_count".to_string(), osdr_count.into());
+    ids.push(osdr_count);
 
-    Ok(Json(serde_json::json!({
-        "apod": apod, "neo": neo, "flr": flr, "cme": cme, "spacex": spacex,
-        "iss": iss_last, "osdr_count": osdr_count
-    })))
-}
\ No newline at end of file
+    let etag = ids.iter().map(i64::to_string).collect::<Vec<_>>().join("-");
+    Ok(versioned(Value::Object(body), etag, last_modified))
+}
@@ -0,0 +1,42 @@
+use axum::extract::Query;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::utils::sun::{night_ring, subsolar_point};
+
+#[derive(Deserialize)]
+pub struct TerminatorQuery {
+    at: Option<DateTime<Utc>>,
+    /// Шаг по долготе в градусах.
+    #[serde(default = "default_step")]
+    step: f64,
+}
+
+fn default_step() -> f64 { 2.0 }
+
+/// Ночная сторона и подсолнечная точка на момент `at` (по умолчанию — сейчас).
+pub async fn terminator(Query(q): Query<TerminatorQuery>) -> Json<Value> {
+    let at = q.at.unwrap_or_else(Utc::now);
+    let (lat, lon) = subsolar_point(at);
+    let ring: Vec<Value> = night_ring(at, q.step.clamp(0.25, 30.0)).iter()
+        .map(|(x, y)| serde_json::json!([x, y]))
+        .collect();
+    Json(serde_json::json!({
+        "type": "FeatureCollection",
+        "properties": { "at": at },
+        "features": [
+            {
+                "type": "Feature",
+                "properties": { "kind": "night" },
+                "geometry": { "type": "Polygon", "coordinates": [ring] },
+            },
+            {
+                "type": "Feature",
+                "properties": { "kind": "subsolar_point" },
+                "geometry": { "type": "Point", "coordinates": [lon, lat] },
+            },
+        ],
+    }))
+}
@@ -0,0 +1,201 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+
+/// Минимальный скачок средней высоты, который считаем манёвром, км.
+const MIN_REBOOST_KM: f64 = 0.3;
+/// Сутки с меньшим числом образцов слишком шумные для среднего.
+const MIN_DAILY_SAMPLES: i64 = 20;
+
+#[derive(Serialize)]
+pub struct DailyAltitude {
+    pub day: NaiveDate,
+    pub mean_km: f64,
+    pub samples: i64,
+}
+
+#[derive(Serialize)]
+pub struct Reboost {
+    pub day: NaiveDate,
+    #[serde(skip)]
+    pub last_day: NaiveDate,
+    pub before_km: f64,
+    pub after_km: f64,
+    pub delta_km: f64,
+}
+
+/// Среднесуточная высота по нормальным (не аномальным) образцам; для уже
+/// свёрнутых суток берётся iss_position_daily.
+pub async fn daily_altitudes(pool: &PgPool, satellite_id: i32, days: i64) -> anyhow::Result<Vec<DailyAltitude>> {
+    let rows = sqlx::query(
+        "SELECT day, sum(mean_km * samples) / sum(samples) AS mean_km, sum(samples)::bigint AS samples
+         FROM (
+             SELECT (observed_at AT TIME ZONE 'UTC')::date AS day, avg(altitude) AS mean_km, count(*) AS samples
+             FROM iss_positions
+             WHERE satellite_id = $1 AND altitude IS NOT NULL AND anomaly IS NULL
+               AND observed_at >= now() - make_interval(days => $2)
+             GROUP BY 1
+             UNION ALL
+             SELECT (bucket AT TIME ZONE 'UTC')::date, avg_alt, samples
+             FROM iss_position_daily
+             WHERE satellite_id = $1 AND avg_alt IS NOT NULL
+               AND bucket >= now() - make_interval(days => $2)
+         ) t
+         GROUP BY day
+         HAVING sum(samples) >= $3
+         ORDER BY day"
+    ).bind(satellite_id).bind(days as i32).bind(MIN_DAILY_SAMPLES)
+    .fetch_all(pool).await?;
+
+    Ok(rows.into_iter().map(|r| DailyAltitude {
+        day: r.get("day"),
+        mean_km: r.get("mean_km"),
+        samples: r.get("samples"),
+    }).collect())
+}
+
+/// Шум — медиана модулей суточных разностей; подъём орбиты — прирост выше 3 шумов.
+pub fn noise_km(daily: &[DailyAltitude]) -> f64 {
+    let mut diffs: Vec<f64> = daily.windows(2).map(|w| (w[1].mean_km - w[0].mean_km).abs()).collect();
+    if diffs.is_empty() {
+        return 0.0;
+    }
+    diffs.sort_by(f64::total_cmp);
+    diffs[diffs.len() / 2]
+}
+
+/// Манёвр посреди суток делит скачок между двумя днями — продолжение
+/// роста выше шума на следующий день присоединяется к тому же событию.
+pub fn find_reboosts(daily: &[DailyAltitude]) -> Vec<Reboost> {
+    let noise = 3.0 * noise_km(daily);
+    let threshold = MIN_REBOOST_KM.max(noise);
+    let mut out: Vec<Reboost> = Vec::new();
+    for w in daily.windows(2) {
+        let delta = w[1].mean_km - w[0].mean_km;
+        match out.last_mut() {
+            Some(last) if last.last_day == w[0].day && delta > noise => {
+                last.last_day = w[1].day;
+                last.after_km = w[1].mean_km;
+                last.delta_km = last.after_km - last.before_km;
+            }
+            _ if delta > threshold => out.push(Reboost {
+                day: w[1].day,
+                last_day: w[1].day,
+                before_km: w[0].mean_km,
+                after_km: w[1].mean_km,
+                delta_km: delta,
+            }),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Скорость снижения, км/сутки: наклон МНК на участках между манёврами,
+/// усреднённый с весом по длине участка. Отрицательное значение — снижение.
+pub fn decay_rate(daily: &[DailyAltitude], reboosts: &[Reboost]) -> Option<f64> {
+    let mut segments: Vec<Vec<&DailyAltitude>> = vec![Vec::new()];
+    for d in daily {
+        if reboosts.iter().any(|r| (r.day..=r.last_day).contains(&d.day)) {
+            segments.push(Vec::new());
+            continue;
+        }
+        segments.last_mut().unwrap().push(d);
+    }
+
+    let (mut sum, mut weight) = (0.0, 0.0);
+    for seg in segments.iter().filter(|s| s.len() >= 3) {
+        let x: Vec<f64> = seg.iter().map(|d| (d.day - seg[0].day).num_days() as f64).collect();
+        let y: Vec<f64> = seg.iter().map(|d| d.mean_km).collect();
+        let n = x.len() as f64;
+        let mx = x.iter().sum::<f64>() / n;
+        let my = y.iter().sum::<f64>() / n;
+        let sxx: f64 = x.iter().map(|v| (v - mx).powi(2)).sum();
+        if sxx == 0.0 {
+            continue;
+        }
+        let sxy: f64 = x.iter().zip(&y).map(|(a, b)| (a - mx) * (b - my)).sum();
+        sum += sxy / sxx * (n - 1.0);
+        weight += n - 1.0;
+    }
+    (weight > 0.0).then(|| sum / weight)
+}
+
+/// Пересчитывает iss_reboost_events по последним `days` суткам: события окна, которые
+/// больше не находятся (пересчитали с флагами аномалий, другим порогом), удаляются.
+pub async fn refresh_reboost_events(pool: &PgPool, satellite_id: i32, days: i64) -> anyhow::Result<usize> {
+    let daily = daily_altitudes(pool, satellite_id, days).await?;
+    let (Some(first), Some(last)) = (daily.first(), daily.last()) else { return Ok(0) };
+    let reboosts = find_reboosts(&daily);
+    let detected: Vec<NaiveDate> = reboosts.iter().map(|r| r.day).collect();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "DELETE FROM iss_reboost_events
+         WHERE satellite_id = $1 AND day BETWEEN $2 AND $3 AND NOT (day = ANY($4))"
+    )
+    .bind(satellite_id).bind(first.day).bind(last.day).bind(&detected)
+    .execute(&mut *tx).await?;
+    for r in &reboosts {
+        sqlx::query(
+            "INSERT INTO iss_reboost_events(satellite_id, day, before_km, after_km, delta_km)
+             VALUES($1,$2,$3,$4,$5)
+             ON CONFLICT (satellite_id, day) DO UPDATE
+             SET before_km = EXCLUDED.before_km, after_km = EXCLUDED.after_km,
+                 delta_km = EXCLUDED.delta_km, detected_at = now()"
+        )
+        .bind(satellite_id).bind(r.day).bind(r.before_km).bind(r.after_km).bind(r.delta_km)
+        .execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(reboosts.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Суточные средние: старт 420 км, снижение 0.05 км/сутки, плюс подъёмы `jumps` (день, км).
+    fn series(days: usize, jumps: &[(usize, f64)]) -> Vec<DailyAltitude> {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let mut km = 420.0;
+        (0..days).map(|i| {
+            if i > 0 {
+                km -= 0.05;
+            }
+            km += jumps.iter().filter(|(d, _)| *d == i).map(|(_, j)| j).sum::<f64>();
+            DailyAltitude { day: start + chrono::Days::new(i as u64), mean_km: km, samples: 100 }
+        }).collect()
+    }
+
+    #[test]
+    fn steady_decay_has_no_reboosts() {
+        let daily = series(10, &[]);
+        assert!(find_reboosts(&daily).is_empty());
+        assert!((decay_rate(&daily, &[]).unwrap() + 0.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn finds_reboost_and_excludes_it_from_decay() {
+        let daily = series(10, &[(5, 2.0)]);
+        let r = find_reboosts(&daily);
+        assert_eq!(r.len(), 1);
+        assert_eq!(r[0].day, daily[5].day);
+        assert!((r[0].delta_km - 1.95).abs() < 1e-9);
+        assert!((decay_rate(&daily, &r).unwrap() + 0.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn manoeuvre_split_across_two_days_is_one_event() {
+        let daily = series(10, &[(5, 1.0), (6, 1.0)]);
+        let r = find_reboosts(&daily);
+        assert_eq!(r.len(), 1);
+        assert_eq!((r[0].day, r[0].last_day), (daily[5].day, daily[6].day));
+        assert!((r[0].delta_km - 1.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn too_few_days_give_no_rate() {
+        assert_eq!(decay_rate(&series(2, &[]), &[]), None);
+    }
+}
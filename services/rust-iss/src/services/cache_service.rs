@@ -0,0 +1,153 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use chrono::Utc;
+use deadpool_redis::redis::AsyncCommands;
+use deadpool_redis::{Connection, Pool};
+use serde_json::Value;
+
+/// Все ключи кэша ответов — под этим префиксом, отдельно от `rl:*`.
+const PREFIX: &str = "cache:";
+/// Дольше ждать Redis нет смысла: база ответит быстрее.
+const TIMEOUT: Duration = Duration::from_millis(200);
+/// После ошибки Redis какое-то время не трогаем, читаем сразу из базы.
+const RETRY_AFTER_SECS: i64 = 30;
+
+static DOWN: Breaker = Breaker(AtomicI64::new(0));
+/// Растёт при каждой инвалидации: ответ, загруженный до неё, в кэш не кладём.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Пока открыт, Redis не трогаем; внутри — unix-время, до которого ждём.
+struct Breaker(AtomicI64);
+
+impl Breaker {
+    fn is_open(&self, now: i64) -> bool {
+        now < self.0.load(Ordering::Relaxed)
+    }
+
+    /// Открывает на `RETRY_AFTER_SECS`; true, если до этого был закрыт.
+    fn trip(&self, now: i64) -> bool {
+        self.0.swap(now + RETRY_AFTER_SECS, Ordering::Relaxed) <= now
+    }
+}
+
+/// Поколение кэша на момент начала загрузки.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Generation(u64);
+
+impl Generation {
+    fn current() -> Self {
+        Generation(GENERATION.load(Ordering::SeqCst))
+    }
+}
+
+/// Read-through: ответ из Redis по `key`, иначе из `load` с записью на `ttl` секунд.
+/// Ошибки `load` не кэшируются; `ttl` 0 — кэш для ответа выключен.
+/// Без Redis просто отдаёт `load`.
+pub async fn cached<E>(redis: &Pool, key: &str, ttl: u64, load: impl Future<Output = Result<Value, E>>) -> Result<Value, E> {
+    if ttl == 0 {
+        return load.await;
+    }
+    let key = format!("{PREFIX}{key}");
+    if let Some(mut conn) = connect(redis).await {
+        let hit: Option<Option<String>> = guard(conn.get(&key)).await;
+        if let Some(v) = hit.flatten().and_then(|s| serde_json::from_str(&s).ok()) {
+            return Ok(v);
+        }
+    }
+
+    let generation = Generation::current();
+    let value = load.await?;
+    if generation == Generation::current() {
+        if let Some(mut conn) = connect(redis).await {
+            let _: Option<()> = guard(conn.set_ex(&key, value.to_string(), ttl as usize)).await;
+        }
+    }
+    Ok(value)
+}
+
+/// Сбрасывает ключи после записи новых данных; `*` в ключе — по шаблону.
+/// Если Redis недоступен, устаревшие ответы доживут до своего TTL.
+pub async fn invalidate(redis: &Pool, keys: &[&str]) {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    let Some(mut conn) = connect(redis).await else { return };
+    let mut doomed = Vec::new();
+    for key in keys {
+        let key = format!("{PREFIX}{key}");
+        if !key.contains('*') {
+            doomed.push(key);
+            continue;
+        }
+        let found: Option<Vec<String>> = guard(async {
+            let mut iter = conn.scan_match::<_, String>(&key).await?;
+            let mut found = Vec::new();
+            while let Some(k) = iter.next_item().await {
+                found.push(k);
+            }
+            Ok(found)
+        }).await;
+        doomed.extend(found.unwrap_or_default());
+    }
+    if !doomed.is_empty() {
+        let _: Option<()> = guard(conn.del(doomed)).await;
+    }
+}
+
+async fn connect(redis: &Pool) -> Option<Connection> {
+    if DOWN.is_open(Utc::now().timestamp()) {
+        return None;
+    }
+    match tokio::time::timeout(TIMEOUT, redis.get()).await {
+        Ok(Ok(conn)) => Some(conn),
+        Ok(Err(e)) => { mark_down(&e.to_string()); None }
+        Err(_) => { mark_down("timeout"); None }
+    }
+}
+
+async fn guard<T>(fut: impl Future<Output = deadpool_redis::redis::RedisResult<T>>) -> Option<T> {
+    match tokio::time::timeout(TIMEOUT, fut).await {
+        Ok(Ok(v)) => Some(v),
+        Ok(Err(e)) => { mark_down(&e.to_string()); None }
+        Err(_) => { mark_down("timeout"); None }
+    }
+}
+
+fn mark_down(err: &str) {
+    if DOWN.trip(Utc::now().timestamp()) {
+        tracing::warn!("redis unavailable ({err}), serving from DB for {RETRY_AFTER_SECS}s");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn invalidate_bumps_generation() {
+        let redis = deadpool_redis::Config::from_url("redis://127.0.0.1:1")
+            .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+            .unwrap();
+        // Redis «лежит» — до сети дело не доходит
+        mark_down("test");
+        let before = Generation::current();
+        invalidate(&redis, &["osdr:*"]).await;
+        let after = Generation::current();
+        assert_ne!(before, after);
+        assert_eq!(after, Generation::current());
+    }
+
+    #[test]
+    fn breaker_stays_open_until_retry() {
+        let b = Breaker(AtomicI64::new(0));
+        assert!(!b.is_open(1_000));
+        assert!(b.trip(1_000));
+        assert!(b.is_open(1_000));
+        assert!(b.is_open(1_000 + RETRY_AFTER_SECS - 1));
+        assert!(!b.is_open(1_000 + RETRY_AFTER_SECS));
+        // повторная ошибка, пока открыт, продлевает без нового предупреждения
+        assert!(!b.trip(1_010));
+        assert!(b.is_open(1_000 + RETRY_AFTER_SECS));
+        assert!(b.trip(1_010 + RETRY_AFTER_SECS));
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, Row, Transaction};
+
+use crate::services::iss_service::backfill_positions;
+
+#[derive(Serialize, Default)]
+pub struct CompactionReport {
+    /// Позиций разобрано из строк журнала без позиции (записанных до iss_positions).
+    pub backfilled: usize,
+    /// Строк журнала, из которых позицию разобрать не удалось: они удаляются без свёртки.
+    pub unparsable: usize,
+    pub days: usize,
+    pub hourly_buckets: u64,
+    pub daily_buckets: u64,
+    pub archived: u64,
+    pub deleted: u64,
+}
+
+/// Свёртка позиций за [from, to) в таблицу агрегатов. Повторная свёртка того же
+/// бакета (например, после дозаполнения пропусков) сливается с уже сохранённой.
+fn rollup_sql(table: &str, unit: &str) -> String {
+    format!(
+        "INSERT INTO {table} AS a (satellite_id, bucket, samples,
+             min_alt, max_alt, avg_alt, min_vel, max_vel, avg_vel,
+             first_at, first_lat, first_lon, last_at, last_lat, last_lon)
+         SELECT satellite_id, date_trunc('{unit}', observed_at, 'UTC'), count(*),
+             min(altitude), max(altitude), avg(altitude),
+             min(velocity), max(velocity), avg(velocity),
+             min(observed_at),
+             (array_agg(lat ORDER BY observed_at))[1], (array_agg(lon ORDER BY observed_at))[1],
+             max(observed_at),
+             (array_agg(lat ORDER BY observed_at DESC))[1], (array_agg(lon ORDER BY observed_at DESC))[1]
+         FROM iss_positions
+         WHERE observed_at >= $1 AND observed_at < $2 AND anomaly IS NULL
+         GROUP BY 1, 2
+         ON CONFLICT (satellite_id, bucket) DO UPDATE SET
+             samples = a.samples + EXCLUDED.samples,
+             min_alt = LEAST(a.min_alt, EXCLUDED.min_alt),
+             max_alt = GREATEST(a.max_alt, EXCLUDED.max_alt),
+             avg_alt = COALESCE((a.avg_alt * a.samples + EXCLUDED.avg_alt * EXCLUDED.samples)
+                                / (a.samples + EXCLUDED.samples), a.avg_alt, EXCLUDED.avg_alt),
+             min_vel = LEAST(a.min_vel, EXCLUDED.min_vel),
+             max_vel = GREATEST(a.max_vel, EXCLUDED.max_vel),
+             avg_vel = COALESCE((a.avg_vel * a.samples + EXCLUDED.avg_vel * EXCLUDED.samples)
+                                / (a.samples + EXCLUDED.samples), a.avg_vel, EXCLUDED.avg_vel),
+             first_lat = CASE WHEN EXCLUDED.first_at < a.first_at THEN EXCLUDED.first_lat ELSE a.first_lat END,
+             first_lon = CASE WHEN EXCLUDED.first_at < a.first_at THEN EXCLUDED.first_lon ELSE a.first_lon END,
+             first_at = LEAST(a.first_at, EXCLUDED.first_at),
+             last_lat = CASE WHEN EXCLUDED.last_at > a.last_at THEN EXCLUDED.last_lat ELSE a.last_lat END,
+             last_lon = CASE WHEN EXCLUDED.last_at > a.last_at THEN EXCLUDED.last_lon ELSE a.last_lon END,
+             last_at = GREATEST(a.last_at, EXCLUDED.last_at)"
+    )
+}
+
+/// Строки журнала за сутки [$1, $2): по времени наблюдения их позиции, а без позиции —
+/// по времени загрузки (такие строки уже прошли через backfill и не разобрались). Журнал удаляется ровно вместе с позициями, попавшими в свёртку
+/// (iss_positions.fetch_id — ON DELETE CASCADE).
+const DAY_LOG_IDS: &str =
+    "SELECT p.fetch_id FROM iss_positions p
+     WHERE p.observed_at >= $1 AND p.observed_at < $2 AND p.fetch_id IS NOT NULL
+     UNION ALL
+     SELECT l.id FROM iss_fetch_log l
+     WHERE l.fetched_at >= $1 AND l.fetched_at < $2
+       AND NOT EXISTS (SELECT 1 FROM iss_positions p WHERE p.fetch_id = l.id)";
+
+/// Сворачивает сырые данные старше `after_days` суток в почасовые и посуточные
+/// агрегаты и удаляет их (или переносит в iss_fetch_log_archive). Каждые сутки —
+/// отдельная транзакция, чтобы не держать долгих блокировок.
+pub async fn compact_iss_log(pool: &PgPool, after_days: i64, archive: bool) -> anyhow::Result<CompactionReport> {
+    let cutoff: DateTime<Utc> = sqlx::query("SELECT date_trunc('day', now() - make_interval(days => $1), 'UTC') AS c")
+        .bind(after_days as i32).fetch_one(pool).await?.get("c");
+    // старые строки без позиции сначала разбираем, иначе их история пропала бы из агрегатов
+    let backfill = backfill_positions(pool, Some(cutoff)).await?;
+    let mut report = CompactionReport {
+        backfilled: backfill.inserted,
+        unparsable: backfill.failed.len(),
+        ..Default::default()
+    };
+    for f in &backfill.failed {
+        tracing::warn!("iss_fetch_log {}: {}, compacted without a position", f.fetch_id, f.reason);
+    }
+
+    loop {
+        // тот же ключ, что в DAY_LOG_IDS, иначе строка журнала с позицией из
+        // более позднего дня держала бы цикл на одних и тех же сутках
+        let day: Option<DateTime<Utc>> = sqlx::query(
+            "SELECT date_trunc('day', LEAST(
+                 (SELECT min(observed_at) FROM iss_positions),
+                 (SELECT min(l.fetched_at) FROM iss_fetch_log l
+                  WHERE NOT EXISTS (SELECT 1 FROM iss_positions p WHERE p.fetch_id = l.id))
+             ), 'UTC') AS d"
+        ).fetch_one(pool).await?.get("d");
+        let Some(day) = day.filter(|d| *d < cutoff) else { break };
+        let next = day + Duration::days(1);
+
+        let mut tx = pool.begin().await?;
+        report.hourly_buckets += sqlx::query(&rollup_sql("iss_position_hourly", "hour"))
+            .bind(day).bind(next).execute(&mut *tx).await?.rows_affected();
+        report.daily_buckets += sqlx::query(&rollup_sql("iss_position_daily", "day"))
+            .bind(day).bind(next).execute(&mut *tx).await?.rows_affected();
+        if archive {
+            report.archived += sqlx::query(&format!(
+                "INSERT INTO iss_fetch_log_archive(id, fetched_at, satellite_id, record)
+                 SELECT id, fetched_at, satellite_id, to_jsonb(l) FROM iss_fetch_log l
+                 WHERE id IN ({DAY_LOG_IDS})
+                 ON CONFLICT (id) DO NOTHING"
+            )).bind(day).bind(next).execute(&mut *tx).await?.rows_affected();
+        }
+        report.deleted += delete_day(&mut tx, day, next).await?;
+        tx.commit().await?;
+        report.days += 1;
+    }
+    Ok(report)
+}
+
+async fn delete_day(tx: &mut Transaction<'_, Postgres>, from: DateTime<Utc>, to: DateTime<Utc>) -> anyhow::Result<u64> {
+    let raw = sqlx::query(&format!("DELETE FROM iss_fetch_log WHERE id IN ({DAY_LOG_IDS})"))
+        .bind(from).bind(to).execute(&mut **tx).await?.rows_affected();
+    // позиции без строки в журнале
+    sqlx::query("DELETE FROM iss_positions WHERE observed_at >= $1 AND observed_at < $2")
+        .bind(from).bind(to).execute(&mut **tx).await?;
+    Ok(raw)
+}
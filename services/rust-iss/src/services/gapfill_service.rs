@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+
+use crate::services::iss_providers::IssProvider;
+use crate::services::iss_service::store_backfilled;
+use crate::utils::orbit::{CircularOrbit, Fix};
+use crate::utils::sun::DEFAULT_ALTITUDE_KM;
+
+/// wheretheiss принимает не больше 10 timestamp'ов за запрос.
+const BATCH_SIZE: usize = 10;
+/// Потолок точек за один проход, чтобы не упереться в rate limit апстрима.
+const MAX_POINTS_PER_RUN: usize = 600;
+/// Дальше круговая орбита без прецессии врёт слишком сильно.
+const MAX_PROPAGATION_SECS: i64 = 6 * 3600;
+
+#[derive(Serialize, Default)]
+pub struct GapFillReport {
+    pub gaps: usize,
+    pub requested: usize,
+    pub from_provider: usize,
+    pub propagated: usize,
+    pub skipped: usize,
+}
+
+struct Gap {
+    before: Fix,
+    start: Fix,
+    end: Fix,
+}
+
+/// Ищет дыры длиннее полутора интервалов опроса за последние `lookback_hours`
+/// и заполняет их точками с шагом `interval_secs`: сначала пакетным запросом
+/// к провайдеру, иначе — по круговой орбите между краями дыры.
+pub async fn fill_gaps(pool: &PgPool, providers: &[IssProvider], satellite_id: i32, interval_secs: u64, lookback_hours: i64) -> anyhow::Result<GapFillReport> {
+    let interval = interval_secs.max(1) as i64;
+    let mut report = GapFillReport::default();
+    let gaps = find_gaps(pool, satellite_id, interval, lookback_hours).await?;
+    report.gaps = gaps.len();
+
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(20)).build()?;
+    for gap in gaps {
+        let mut targets: Vec<DateTime<Utc>> = Vec::new();
+        let mut t = gap.start.at + chrono::Duration::seconds(interval);
+        while t < gap.end.at - chrono::Duration::seconds(interval / 2) && report.requested + targets.len() < MAX_POINTS_PER_RUN {
+            targets.push(t);
+            t += chrono::Duration::seconds(interval);
+        }
+        if targets.is_empty() {
+            continue;
+        }
+        report.requested += targets.len();
+
+        let mut missing = Vec::new();
+        for chunk in targets.chunks(BATCH_SIZE) {
+            match fetch_batch(&client, pool, providers, satellite_id, chunk).await {
+                Ok(n) => report.from_provider += n,
+                Err(e) => {
+                    tracing::warn!("gap fill (norad {satellite_id}): {e}");
+                    missing.extend_from_slice(chunk);
+                }
+            }
+        }
+        if missing.is_empty() {
+            continue;
+        }
+
+        let orbit = ((gap.end.at - gap.start.at).num_seconds() <= MAX_PROPAGATION_SECS)
+            .then(|| CircularOrbit::fit(gap.before, gap.start, gap.end))
+            .flatten();
+        let Some(orbit) = orbit else {
+            report.skipped += missing.len();
+            continue;
+        };
+        for at in missing {
+            let f = orbit.position(at);
+            let payload = serde_json::json!({
+                "latitude": f.lat, "longitude": f.lon, "altitude": f.altitude,
+                "timestamp": at.timestamp(), "method": "circular_orbit",
+            });
+            report.propagated += store_backfilled(pool, "propagation", "propagation:circular_orbit", &payload, &payload, satellite_id, at).await? as usize;
+        }
+        if report.requested >= MAX_POINTS_PER_RUN {
+            break;
+        }
+    }
+    Ok(report)
+}
+
+async fn find_gaps(pool: &PgPool, satellite_id: i32, interval: i64, lookback_hours: i64) -> anyhow::Result<Vec<Gap>> {
+    let rows = sqlx::query(
+        "SELECT * FROM (
+             SELECT observed_at, lat, lon, altitude,
+                    LAG(observed_at) OVER w AS prev_at, LAG(lat) OVER w AS prev_lat,
+                    LAG(lon) OVER w AS prev_lon, LAG(altitude) OVER w AS prev_alt,
+                    LAG(observed_at, 2) OVER w AS before_at, LAG(lat, 2) OVER w AS before_lat,
+                    LAG(lon, 2) OVER w AS before_lon
+             FROM iss_positions
+             WHERE satellite_id = $1
+               AND observed_at >= now() - make_interval(hours => $2::int)
+             WINDOW w AS (ORDER BY observed_at)
+         ) t
+         WHERE prev_at IS NOT NULL AND observed_at - prev_at > make_interval(secs => $3::float8 * 1.5)
+         ORDER BY observed_at"
+    )
+    .bind(satellite_id).bind(lookback_hours as i32).bind(interval as f64)
+    .fetch_all(pool).await?;
+
+    Ok(rows.into_iter().map(|r| {
+        let prev_alt = r.get::<Option<f64>, _>("prev_alt").unwrap_or(DEFAULT_ALTITUDE_KM);
+        let start = Fix { at: r.get("prev_at"), lat: r.get("prev_lat"), lon: r.get("prev_lon"), altitude: prev_alt };
+        let before = match r.get::<Option<DateTime<Utc>>, _>("before_at") {
+            Some(at) => Fix { at, lat: r.get("before_lat"), lon: r.get("before_lon"), altitude: prev_alt },
+            None => start,
+        };
+        Gap {
+            before,
+            start,
+            end: Fix {
+                at: r.get("observed_at"), lat: r.get("lat"), lon: r.get("lon"),
+                altitude: r.get::<Option<f64>, _>("altitude").unwrap_or(prev_alt),
+            },
+        }
+    }).collect())
+}
+
+/// Первый провайдер с пакетным эндпоинтом, ответивший без ошибки.
+async fn fetch_batch(client: &reqwest::Client, pool: &PgPool, providers: &[IssProvider], satellite_id: i32, at: &[DateTime<Utc>]) -> anyhow::Result<usize> {
+    let ts: Vec<i64> = at.iter().map(DateTime::timestamp).collect();
+    let mut errors = Vec::new();
+    for p in providers {
+        let Some(url) = p.positions_url_for(satellite_id, &ts) else { continue };
+        let items = match fetch_positions(client, &url).await {
+            Ok(items) => items,
+            Err(e) => {
+                errors.push(format!("{}: {e}", p.name));
+                continue;
+            }
+        };
+        let mut stored = 0;
+        for raw in &items {
+            // одна битая позиция не должна срывать всю пачку
+            let json = match p.normalize(raw) {
+                Ok(json) => json,
+                Err(e) => {
+                    tracing::warn!("gap fill (norad {satellite_id}, {}): skipping position: {e}", p.name);
+                    continue;
+                }
+            };
+            let Some(when) = json["timestamp"].as_i64().and_then(|s| DateTime::from_timestamp(s, 0)) else { continue };
+            stored += store_backfilled(pool, &p.name, &url, raw, &json, satellite_id, when).await? as usize;
+        }
+        return Ok(stored);
+    }
+    if errors.is_empty() {
+        anyhow::bail!("no ISS provider supports batch positions");
+    }
+    anyhow::bail!("batch positions failed: {}", errors.join("; "))
+}
+
+async fn fetch_positions(client: &reqwest::Client, url: &str) -> anyhow::Result<Vec<Value>> {
+    let resp = client.get(url).send().await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("HTTP {}", resp.status());
+    }
+    match resp.json::<Value>().await? {
+        Value::Array(items) => Ok(items),
+        other => anyhow::bail!("expected an array of positions, got {other}"),
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+
+use crate::services::iss_service::Position;
+use crate::utils::geo::haversine_km;
+
+/// Орбитальная скорость МКС ~27 600 км/ч; наземная скорость не больше неё,
+/// запас на погрешность координат и времени.
+const MAX_GROUND_SPEED_KMH: f64 = 27_600.0 * 1.25;
+const ALTITUDE_KM: std::ops::RangeInclusive<f64> = 150.0..=1000.0;
+/// Столько подряд образцов, отбракованных только по скорости, — повод усомниться в опорном.
+const REANCHOR_AFTER: usize = 3;
+
+pub struct PrevSample {
+    pub observed_at: DateTime<Utc>,
+    pub lat: f64,
+    pub lon: f64,
+    pub anomaly: Option<String>,
+}
+
+/// Образец, с которым сравнивается новый: см. [`anchor`].
+pub async fn previous_sample(pool: &PgPool, satellite_id: i32, before: DateTime<Utc>) -> anyhow::Result<Option<PrevSample>> {
+    let sample = |r: sqlx::postgres::PgRow| PrevSample {
+        observed_at: r.get("observed_at"), lat: r.get("lat"), lon: r.get("lon"), anomaly: r.get("anomaly"),
+    };
+    let last_ok = sqlx::query(
+        "SELECT observed_at, lat, lon, anomaly FROM iss_positions
+         WHERE satellite_id = $1 AND observed_at <= $2 AND anomaly IS NULL
+         ORDER BY observed_at DESC, id DESC LIMIT 1"
+    ).bind(satellite_id).bind(before).fetch_optional(pool).await?.map(sample);
+    let recent: Vec<PrevSample> = sqlx::query(
+        "SELECT observed_at, lat, lon, anomaly FROM iss_positions
+         WHERE satellite_id = $1 AND observed_at <= $2
+         ORDER BY observed_at DESC, id DESC LIMIT $3"
+    ).bind(satellite_id).bind(before).bind(REANCHOR_AFTER as i64).fetch_all(pool).await?
+        .into_iter().map(sample).collect();
+    Ok(anchor(last_ok, recent))
+}
+
+/// Обычно опора — последний нормальный образец. Но если после него `REANCHOR_AFTER`
+/// образцов подряд отбракованы только по скорости и при этом согласуются между собой,
+/// сбойным был скорее сам опорный (например, первый сохранённый) — опорой
+/// становится последний из них. `recent` — последние образцы, от новых к старым.
+pub fn anchor(last_ok: Option<PrevSample>, mut recent: Vec<PrevSample>) -> Option<PrevSample> {
+    let streak = recent.len() == REANCHOR_AFTER
+        && recent.iter().all(|s| {
+            s.anomaly.as_deref() == Some("speed")
+                && last_ok.as_ref().is_none_or(|ok| s.observed_at > ok.observed_at)
+        })
+        && recent.windows(2).all(|w| plausible(&w[1], w[0].observed_at, w[0].lat, w[0].lon));
+    if streak {
+        return Some(recent.swap_remove(0));
+    }
+    last_ok
+}
+
+/// Причины, по которым образец физически неправдоподобен; `None` — образец в порядке.
+pub fn detect(prev: Option<&PrevSample>, p: &Position) -> Option<String> {
+    let mut reasons = Vec::new();
+    if let Some(alt) = p.altitude {
+        if !ALTITUDE_KM.contains(&alt) {
+            reasons.push("altitude");
+        }
+    }
+    if let Some(prev) = prev {
+        if p.observed_at <= prev.observed_at {
+            reasons.push("duplicate_timestamp");
+        } else if !plausible(prev, p.observed_at, p.lat, p.lon) {
+            reasons.push("speed");
+        }
+    }
+    (!reasons.is_empty()).then(|| reasons.join(","))
+}
+
+/// Мог ли спутник долететь от `prev` до точки к моменту `at`.
+fn plausible(prev: &PrevSample, at: DateTime<Utc>, lat: f64, lon: f64) -> bool {
+    let dt_h = (at - prev.observed_at).num_milliseconds() as f64 / 3_600_000.0;
+    dt_h > 0.0 && haversine_km(prev.lat, prev.lon, lat, lon) / dt_h <= MAX_GROUND_SPEED_KMH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn t(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_760_000_000 + secs, 0).unwrap()
+    }
+
+    fn prev(secs: i64, lon: f64, anomaly: Option<&str>) -> PrevSample {
+        PrevSample { observed_at: t(secs), lat: 0.0, lon, anomaly: anomaly.map(String::from) }
+    }
+
+    fn pos(secs: i64, lon: f64, altitude: Option<f64>) -> Position {
+        Position {
+            observed_at: t(secs), lat: 0.0, lon, altitude,
+            velocity: None, visibility: None, footprint: None,
+        }
+    }
+
+    #[test]
+    fn orbital_speed_passes() {
+        // 4° по экватору за минуту — ~26 700 км/ч
+        assert_eq!(detect(Some(&prev(0, 0.0, None)), &pos(60, 4.0, Some(420.0))), None);
+        assert_eq!(detect(None, &pos(0, 0.0, Some(420.0))), None);
+    }
+
+    #[test]
+    fn flags_speed_altitude_and_duplicates() {
+        // 10° за минуту — ~66 700 км/ч
+        assert_eq!(detect(Some(&prev(0, 0.0, None)), &pos(60, 10.0, None)).as_deref(), Some("speed"));
+        assert_eq!(detect(None, &pos(0, 0.0, Some(50.0))).as_deref(), Some("altitude"));
+        assert_eq!(detect(Some(&prev(60, 0.0, None)), &pos(60, 0.0, Some(2000.0))).as_deref(), Some("altitude,duplicate_timestamp"));
+        assert_eq!(detect(Some(&prev(0, 0.0, None)), &pos(0, 0.0, None)).as_deref(), Some("duplicate_timestamp"));
+    }
+
+    #[test]
+    fn keeps_last_ok_without_streak() {
+        let got = anchor(Some(prev(0, 0.0, None)), vec![prev(60, 50.0, Some("speed")), prev(0, 0.0, None)]);
+        assert_eq!(got.unwrap().observed_at, t(0));
+    }
+
+    #[test]
+    fn reanchors_after_consistent_speed_streak() {
+        // опорный образец сбойный: следующие три согласованы между собой, но далеко от него
+        let recent = vec![prev(180, 62.0, Some("speed")), prev(120, 58.0, Some("speed")), prev(60, 54.0, Some("speed"))];
+        let got = anchor(Some(prev(0, 0.0, None)), recent).unwrap();
+        assert_eq!(got.observed_at, t(180));
+        assert_eq!(got.lon, 62.0);
+        assert!(detect(Some(&got), &pos(240, 66.0, None)).is_none());
+    }
+
+    #[test]
+    fn no_reanchor_on_scattered_garbage() {
+        let recent = vec![prev(180, -120.0, Some("speed")), prev(120, 58.0, Some("speed")), prev(60, 170.0, Some("speed"))];
+        assert_eq!(anchor(Some(prev(0, 0.0, None)), recent).unwrap().observed_at, t(0));
+        let recent = vec![prev(180, 62.0, Some("altitude,speed")), prev(120, 58.0, Some("speed")), prev(60, 54.0, Some("speed"))];
+        assert_eq!(anchor(Some(prev(0, 0.0, None)), recent).unwrap().observed_at, t(0));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use crate::services::iss_service::ISS_NORAD_ID;
+use crate::utils::mapping::{self, FieldRule, FieldType, SourceMapping};
+
+/// Поля позиции, которые провайдер может отдать; широта и долгота обязательны.
+pub const ISS_FIELDS: &[(&str, FieldType)] = &[
+    ("latitude", FieldType::Number),
+    ("longitude", FieldType::Number),
+    ("altitude", FieldType::Number),
+    ("velocity", FieldType::Number),
+    ("visibility", FieldType::String),
+    ("footprint", FieldType::Number),
+    ("timestamp", FieldType::Timestamp),
+];
+const REQUIRED: &[&str] = &["latitude", "longitude"];
+
+/// Источник позиций ISS. `url` может содержать `{id}` (NORAD ID),
+/// `iss_url` переопределяет адрес для самой МКС.
+/// `positions_url` — пакетный запрос прошлых позиций (`{id}`, `{timestamps}`).
+/// `fields` — правила источника `iss_<name>` из field_mappings.json.
+#[derive(Clone)]
+pub struct IssProvider {
+    pub name: String,
+    pub url: String,
+    pub iss_url: Option<String>,
+    pub positions_url: Option<String>,
+    pub fields: SourceMapping,
+}
+
+impl IssProvider {
+    pub fn url_for(&self, satellite_id: i32) -> Option<String> {
+        if satellite_id == ISS_NORAD_ID {
+            if let Some(u) = &self.iss_url { return Some(u.clone()); }
+        }
+        if self.url.contains("{id}") {
+            return Some(self.url.replace("{id}", &satellite_id.to_string()));
+        }
+        (satellite_id == ISS_NORAD_ID).then(|| self.url.clone())
+    }
+
+    pub fn positions_url_for(&self, satellite_id: i32, timestamps: &[i64]) -> Option<String> {
+        let ts: Vec<String> = timestamps.iter().map(i64::to_string).collect();
+        self.positions_url.as_ref().map(|u| u.replace("{id}", &satellite_id.to_string()).replace("{timestamps}", &ts.join(",")))
+    }
+
+    /// Поля позиции из ответа в формате wheretheiss (latitude/longitude/...,
+    /// timestamp — unix-секунды); сам ответ не меняется и хранится отдельно.
+    pub fn normalize(&self, raw: &Value) -> Result<Value, String> {
+        let rec = self.fields.apply(self.fields.resolve_root(raw, &[]));
+        let lat = rec.number("latitude").ok_or_else(|| format!("{}: no latitude", self.name))?;
+        let lon = rec.number("longitude").ok_or_else(|| format!("{}: no longitude", self.name))?;
+
+        let mut out = Map::new();
+        out.insert("latitude".into(), lat.into());
+        out.insert("longitude".into(), lon.into());
+        for key in ["altitude", "velocity", "footprint"] {
+            if let Some(x) = rec.number(key) {
+                out.insert(key.into(), x.into());
+            }
+        }
+        if let Some(s) = rec.text("visibility") {
+            out.insert("visibility".into(), s.into());
+        }
+        if let Some(t) = rec.time("timestamp") {
+            out.insert("timestamp".into(), t.timestamp().into());
+        }
+        Ok(Value::Object(out))
+    }
+}
+
+/// Ошибки правил провайдера ISS; необязательных полей может не быть.
+pub fn check_fields(rules: &SourceMapping) -> Vec<String> {
+    let targets: Vec<(&str, FieldType)> = ISS_FIELDS.iter().copied()
+        .filter(|(f, _)| REQUIRED.contains(f) || rules.fields.contains_key(*f))
+        .collect();
+    rules.check(&targets)
+}
+
+/// Правила `iss_<name>` с проверкой: кривые правила лучше поймать при старте.
+fn provider_fields(name: &str, rules: &SourceMapping) -> anyhow::Result<SourceMapping> {
+    let errors = check_fields(rules);
+    if !errors.is_empty() {
+        anyhow::bail!("field mapping iss_{name}: {}", errors.join("; "));
+    }
+    Ok(rules.clone())
+}
+
+fn wheretheiss(where_iss_url: &str, satellite_url: &str) -> anyhow::Result<IssProvider> {
+    Ok(IssProvider {
+        name: "wheretheiss".into(),
+        url: satellite_url.into(),
+        iss_url: Some(where_iss_url.into()),
+        positions_url: Some(format!("{satellite_url}/positions?timestamps={{timestamps}}")),
+        fields: provider_fields("wheretheiss", mapping::source("iss_wheretheiss"))?,
+    })
+}
+
+// open-notify отдаёт только МКС и координаты строками
+fn open_notify(url: &str) -> anyhow::Result<IssProvider> {
+    Ok(IssProvider {
+        name: "open-notify".into(),
+        url: url.into(),
+        iss_url: None,
+        positions_url: None,
+        fields: provider_fields("open-notify", mapping::source("iss_open-notify"))?,
+    })
+}
+
+/// `ISS_CUSTOM_FIELDS` (`latitude=/a/b,longitude=/c,...`) — краткая запись правил;
+/// без неё берётся источник `iss_custom` из `FIELD_MAPPINGS_PATH`.
+fn custom_fields(spec: &str) -> anyhow::Result<SourceMapping> {
+    if spec.trim().is_empty() {
+        let rules = mapping::find("iss_custom")
+            .ok_or_else(|| anyhow::anyhow!("custom provider needs ISS_CUSTOM_FIELDS or an iss_custom field mapping"))?;
+        return provider_fields("custom", rules);
+    }
+    let mut fields = BTreeMap::new();
+    for pair in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (k, v) = pair.split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("bad field mapping {pair}, expected key=/json/pointer"))?;
+        let (k, v) = (k.trim(), v.trim());
+        let &(_, kind) = ISS_FIELDS.iter().find(|(f, _)| *f == k)
+            .ok_or_else(|| anyhow::anyhow!("unknown field {k} in ISS_CUSTOM_FIELDS"))?;
+        // время строкой: RFC 3339, «дата время» или unix-секунды
+        let formats = match kind {
+            FieldType::Timestamp => vec!["%Y-%m-%d %H:%M:%S".to_string(), "%s".to_string()],
+            _ => Vec::new(),
+        };
+        fields.insert(k.to_string(), FieldRule { paths: vec![v.to_string()], kind, formats, split: None, join: None });
+    }
+    provider_fields("custom", &SourceMapping { root: Vec::new(), fields })
+}
+
+/// Цепочка провайдеров из `ISS_PROVIDERS` в порядке приоритета.
+pub fn providers_from_env(where_iss_url: &str, satellite_url: &str) -> anyhow::Result<Vec<IssProvider>> {
+    let list = std::env::var("ISS_PROVIDERS").unwrap_or_else(|_| "wheretheiss,open-notify".to_string());
+    let mut out = Vec::new();
+    for name in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        match name {
+            "wheretheiss" => out.push(wheretheiss(where_iss_url, satellite_url)?),
+            "open-notify" => out.push(open_notify(
+                &std::env::var("OPEN_NOTIFY_URL").unwrap_or_else(|_| "http://api.open-notify.org/iss-now.json".to_string()),
+            )?),
+            "custom" => out.push(IssProvider {
+                name: "custom".into(),
+                url: std::env::var("ISS_CUSTOM_URL").map_err(|_| anyhow::anyhow!("ISS_CUSTOM_URL is required for custom provider"))?,
+                iss_url: None,
+                positions_url: None,
+                fields: custom_fields(&std::env::var("ISS_CUSTOM_FIELDS").unwrap_or_default())?,
+            }),
+            other => anyhow::bail!("unknown ISS provider {other}"),
+        }
+    }
+    if out.is_empty() {
+        anyhow::bail!("ISS_PROVIDERS is empty");
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalizes_wheretheiss() {
+        let p = wheretheiss("http://iss", "http://sat/{id}").unwrap();
+        let raw = json!({
+            "name": "iss", "id": 25544, "latitude": 50.1, "longitude": -3.5, "altitude": 418.2,
+            "velocity": 27580.1, "visibility": "daylight", "footprint": 4500.0, "timestamp": 1_700_000_000,
+        });
+        assert_eq!(p.normalize(&raw).unwrap(), json!({
+            "latitude": 50.1, "longitude": -3.5, "altitude": 418.2, "velocity": 27580.1,
+            "visibility": "daylight", "footprint": 4500.0, "timestamp": 1_700_000_000,
+        }));
+    }
+
+    #[test]
+    fn normalizes_open_notify_strings() {
+        let p = open_notify("http://on").unwrap();
+        let raw = json!({ "message": "success", "timestamp": 1_700_000_000,
+                          "iss_position": { "latitude": "-12.5", "longitude": "100.25" } });
+        assert_eq!(p.normalize(&raw).unwrap(), json!({ "latitude": -12.5, "longitude": 100.25, "timestamp": 1_700_000_000 }));
+        assert!(p.normalize(&json!({ "iss_position": { "latitude": "x" } })).is_err());
+    }
+
+    #[test]
+    fn custom_field_spec() {
+        let fields = custom_fields("latitude=/pos/0, longitude=/pos/1, timestamp=/ts").unwrap();
+        let p = IssProvider { name: "custom".into(), url: "http://c".into(), iss_url: None, positions_url: None, fields };
+        let out = p.normalize(&json!({ "pos": [1.5, 2.5], "ts": "1700000000" })).unwrap();
+        assert_eq!(out, json!({ "latitude": 1.5, "longitude": 2.5, "timestamp": 1_700_000_000 }));
+        let out = p.normalize(&json!({ "pos": [1.5, 2.5], "ts": "2023-11-14 22:13:20" })).unwrap();
+        assert_eq!(out["timestamp"], 1_700_000_000);
+
+        assert!(custom_fields("latitude=/a").is_err()); // нет longitude
+        assert!(custom_fields("latitude=/a,longitude=/b,speed=/c").is_err());
+        assert!(custom_fields("latitude=/a,longitude").is_err());
+    }
+
+    #[test]
+    fn urls() {
+        let p = wheretheiss("http://iss", "http://sat/{id}").unwrap();
+        assert_eq!(p.url_for(ISS_NORAD_ID).as_deref(), Some("http://iss"));
+        assert_eq!(p.url_for(20580).as_deref(), Some("http://sat/20580"));
+        assert_eq!(p.positions_url_for(20580, &[1, 2]).as_deref(), Some("http://sat/20580/positions?timestamps=1,2"));
+        assert_eq!(open_notify("http://on").unwrap().url_for(20580), None);
+    }
+}
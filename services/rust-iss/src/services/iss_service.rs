@@ -1,13 +1,259 @@
 use std::time::Duration;
+use chrono::{DateTime, Utc};
 use reqwest;
+use serde::Serialize;
 use serde_json::Value;
-use sqlx::PgPool;
+use sqlx::{PgPool, Row};
 
-pub async fn fetch_and_store_iss(pool: &PgPool, url: &str) -> anyhow::Result<()> {
+use crate::services::iss_anomaly::{detect, previous_sample};
+use crate::services::iss_providers::IssProvider;
+use crate::services::region_service::evaluate_regions;
+use crate::utils::geocode::lookup;
+use crate::utils::helpers::{num, s_pick};
+
+/// NORAD ID МКС — спутник по умолчанию для всех ISS-ручек.
+pub const ISS_NORAD_ID: i32 = 25544;
+
+/// Позиция, извлечённая из сырого payload.
+pub struct Position {
+    pub observed_at: DateTime<Utc>,
+    pub lat: f64,
+    pub lon: f64,
+    pub altitude: Option<f64>,
+    pub velocity: Option<f64>,
+    pub visibility: Option<String>,
+    pub footprint: Option<f64>,
+}
+
+/// Позиция из нормализованного документа (см. [`IssProvider::normalize`]): время
+/// наблюдения уже приведено правилами провайдера к unix-секундам, без него — момент записи.
+pub fn parse_position(payload: &Value, fetched_at: DateTime<Utc>) -> Result<Position, String> {
+    let lat = num(&payload["latitude"]).ok_or("no latitude")?;
+    let lon = num(&payload["longitude"]).ok_or("no longitude")?;
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(format!("coordinates out of range: {lat}, {lon}"));
+    }
+    Ok(Position {
+        observed_at: payload["timestamp"].as_i64().and_then(|s| DateTime::from_timestamp(s, 0)).unwrap_or(fetched_at),
+        lat,
+        lon,
+        altitude: num(&payload["altitude"]),
+        velocity: num(&payload["velocity"]),
+        visibility: s_pick(payload, &["visibility"]),
+        footprint: num(&payload["footprint"]),
+    })
+}
+
+async fn store_position(pool: &PgPool, fetch_id: i64, satellite_id: i32, p: &Position, origin: &str) -> anyhow::Result<u64> {
+    let prev = previous_sample(pool, satellite_id, p.observed_at).await?;
+    let anomaly = detect(prev.as_ref(), p);
+    if let Some(a) = &anomaly {
+        tracing::warn!("iss_fetch_log {fetch_id}: anomalous sample ({a})");
+    }
+    let place = lookup(p.lat, p.lon);
+    let res = sqlx::query(
+        "INSERT INTO iss_positions(fetch_id, satellite_id, observed_at, lat, lon, altitude, velocity, visibility, footprint, anomaly,
+                                   place, place_iso, place_kind, origin)
+         VALUES($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13,$14)
+         ON CONFLICT (fetch_id) DO NOTHING"
+    )
+    .bind(fetch_id).bind(satellite_id).bind(p.observed_at)
+    .bind(p.lat).bind(p.lon).bind(p.altitude).bind(p.velocity)
+    .bind(&p.visibility).bind(p.footprint).bind(anomaly)
+    .bind(place.as_ref().map(|x| &x.name)).bind(place.as_ref().and_then(|x| x.iso.as_ref()))
+    .bind(place.as_ref().map(|x| &x.kind)).bind(origin)
+    .execute(pool).await?;
+    Ok(res.rows_affected())
+}
+
+/// Опрашивает провайдеров по порядку и сохраняет первый удачный ответ.
+pub async fn fetch_and_store_iss(pool: &PgPool, providers: &[IssProvider], satellite_id: i32) -> anyhow::Result<()> {
     let client = reqwest::Client::builder().timeout(Duration::from_secs(20)).build()?;
+    let mut errors = Vec::new();
+    for p in providers {
+        let Some(url) = p.url_for(satellite_id) else { continue };
+        match fetch_from(&client, p, &url).await {
+            Ok((raw, normalized)) => return store_fetch(pool, &p.name, &url, &raw, &normalized, satellite_id).await,
+            Err(e) => {
+                tracing::warn!("ISS provider {} failed for {satellite_id}: {e}", p.name);
+                errors.push(format!("{}: {e}", p.name));
+            }
+        }
+    }
+    if errors.is_empty() {
+        anyhow::bail!("no ISS provider supports satellite {satellite_id}");
+    }
+    anyhow::bail!("all ISS providers failed: {}", errors.join("; "))
+}
+
+/// Ответ провайдера и нормализованные из него поля позиции.
+async fn fetch_from(client: &reqwest::Client, p: &IssProvider, url: &str) -> anyhow::Result<(Value, Value)> {
     let resp = client.get(url).send().await?;
-    let json: Value = resp.json().await?;
-    sqlx::query("INSERT INTO iss_fetch_log (source_url, payload) VALUES ($1, $2)")
-        .bind(url).bind(json).execute(pool).await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("HTTP {}", resp.status());
+    }
+    let raw: Value = resp.json().await?;
+    let normalized = p.normalize(&raw).map_err(anyhow::Error::msg)?;
+    Ok((raw, normalized))
+}
+
+async fn store_fetch(pool: &PgPool, provider: &str, url: &str, raw: &Value, normalized: &Value, satellite_id: i32) -> anyhow::Result<()> {
+    let row = sqlx::query(
+        "INSERT INTO iss_fetch_log (source_url, provider, payload, normalized, satellite_id) VALUES ($1, $2, $3, $4, $5) RETURNING id, fetched_at"
+    )
+        .bind(url).bind(provider).bind(raw).bind(normalized).bind(satellite_id).fetch_one(pool).await?;
+    let id: i64 = row.get("id");
+    let fetched_at: DateTime<Utc> = row.get("fetched_at");
+
+    let pos = match parse_position(normalized, fetched_at) {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::warn!("iss_fetch_log {id}: cannot parse position: {e}");
+            return Ok(());
+        }
+    };
+    store_position(pool, id, satellite_id, &pos, "live").await?;
+
+    // геозоны описывают именно МКС; время события — момент наблюдения, как в /iss/history
+    if satellite_id == ISS_NORAD_ID {
+        evaluate_regions(pool, id, pos.observed_at, pos.lat, pos.lon).await?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Сохраняет позицию, восстановленную задним числом: `fetched_at` = момент наблюдения,
+/// геозоны не пересчитываются (события вышли бы не по порядку).
+pub async fn store_backfilled(pool: &PgPool, provider: &str, url: &str, raw: &Value, normalized: &Value, satellite_id: i32, at: DateTime<Utc>) -> anyhow::Result<u64> {
+    let pos = parse_position(normalized, at).map_err(anyhow::Error::msg)?;
+    let row = sqlx::query(
+        "INSERT INTO iss_fetch_log (fetched_at, source_url, provider, payload, normalized, satellite_id, origin)
+         VALUES ($1, $2, $3, $4, $5, $6, 'backfilled') RETURNING id"
+    )
+        .bind(pos.observed_at).bind(url).bind(provider).bind(raw).bind(normalized).bind(satellite_id)
+        .fetch_one(pool).await?;
+    let id: i64 = row.get("id");
+    store_position(pool, id, satellite_id, &pos, "backfilled").await
+}
+
+#[derive(Serialize, Default)]
+pub struct BackfillReport {
+    pub scanned: usize,
+    pub inserted: usize,
+    pub failed: Vec<BackfillFailure>,
+}
+
+#[derive(Serialize)]
+pub struct BackfillFailure {
+    pub fetch_id: i64,
+    pub reason: String,
+}
+
+/// Заполняет iss_positions по строкам iss_fetch_log, у которых ещё нет позиции;
+/// `before` ограничивает строки загруженными раньше этого момента.
+pub async fn backfill_positions(pool: &PgPool, before: Option<DateTime<Utc>>) -> anyhow::Result<BackfillReport> {
+    let mut report = BackfillReport::default();
+    let mut last_id = 0i64;
+    loop {
+        let rows = sqlx::query(
+            "SELECT l.id, l.fetched_at, COALESCE(l.normalized, l.payload) AS payload, l.satellite_id, l.origin
+             FROM iss_fetch_log l
+             LEFT JOIN iss_positions p ON p.fetch_id = l.id
+             WHERE p.id IS NULL AND l.id > $1 AND ($2::timestamptz IS NULL OR l.fetched_at < $2)
+             ORDER BY l.id
+             LIMIT 1000"
+        ).bind(last_id).bind(before).fetch_all(pool).await?;
+        if rows.is_empty() {
+            break;
+        }
+
+        for r in rows {
+            let id: i64 = r.get("id");
+            last_id = id;
+            report.scanned += 1;
+            let payload: Value = r.get("payload");
+            match parse_position(&payload, r.get("fetched_at")) {
+                Ok(pos) => report.inserted += store_position(pool, id, r.get("satellite_id"), &pos, r.get("origin")).await? as usize,
+                Err(reason) => report.failed.push(BackfillFailure { fetch_id: id, reason }),
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Проставляет place у позиций, сохранённых до появления геокодера.
+pub async fn geocode_positions(pool: &PgPool) -> anyhow::Result<u64> {
+    let mut updated = 0u64;
+    let mut last_id = 0i64;
+    loop {
+        let rows = sqlx::query(
+            "SELECT id, lat, lon FROM iss_positions WHERE place IS NULL AND id > $1 ORDER BY id LIMIT 1000"
+        ).bind(last_id).fetch_all(pool).await?;
+        if rows.is_empty() {
+            break;
+        }
+
+        for r in rows {
+            last_id = r.get("id");
+            let Some(place) = lookup(r.get("lat"), r.get("lon")) else { continue };
+            updated += sqlx::query("UPDATE iss_positions SET place = $2, place_iso = $3, place_kind = $4 WHERE id = $1")
+                .bind(last_id).bind(&place.name).bind(&place.iso).bind(&place.kind)
+                .execute(pool).await?
+                .rows_affected();
+        }
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mapping;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn fetched() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn wheretheiss_payload() {
+        let p = parse_position(&json!({
+            "name": "iss", "latitude": 51.5, "longitude": -0.12, "altitude": 419.8, "velocity": 27560.2,
+            "visibility": "eclipsed", "footprint": 4510.1, "timestamp": 1_700_000_000,
+        }), fetched()).unwrap();
+        assert_eq!(p.observed_at, Utc.timestamp_opt(1_700_000_000, 0).unwrap());
+        assert_eq!((p.lat, p.lon), (51.5, -0.12));
+        assert_eq!((p.altitude, p.velocity, p.footprint), (Some(419.8), Some(27560.2), Some(4510.1)));
+        assert_eq!(p.visibility.as_deref(), Some("eclipsed"));
+    }
+
+    #[test]
+    fn open_notify_payload() {
+        let provider = IssProvider {
+            name: "open-notify".into(), url: "http://on".into(), iss_url: None, positions_url: None,
+            fields: mapping::source("iss_open-notify").clone(),
+        };
+        let raw = json!({ "message": "success", "timestamp": 1_700_000_060,
+                          "iss_position": { "latitude": "-33.8675", "longitude": "151.2070" } });
+        let p = parse_position(&provider.normalize(&raw).unwrap(), fetched()).unwrap();
+        assert_eq!(p.observed_at, Utc.timestamp_opt(1_700_000_060, 0).unwrap());
+        assert_eq!((p.lat, p.lon), (-33.8675, 151.207));
+        assert_eq!((p.altitude, p.velocity, p.visibility), (None, None, None));
+    }
+
+    #[test]
+    fn string_coordinates_and_fetch_time_fallback() {
+        let p = parse_position(&json!({ "latitude": "10.5", "longitude": "-20" }), fetched()).unwrap();
+        assert_eq!((p.lat, p.lon), (10.5, -20.0));
+        assert_eq!(p.observed_at, fetched());
+    }
+
+    #[test]
+    fn rejects_bad_coordinates() {
+        assert!(parse_position(&json!({ "latitude": 91.0, "longitude": 0.0 }), fetched()).is_err());
+        assert!(parse_position(&json!({ "latitude": 0.0, "longitude": -180.5 }), fetched()).is_err());
+        assert!(parse_position(&json!({ "latitude": "north", "longitude": 0.0 }), fetched()).is_err());
+        assert!(parse_position(&json!({ "longitude": 0.0 }), fetched()).is_err());
+        assert!(parse_position(&json!({ "latitude": 90.0, "longitude": 180.0 }), fetched()).is_ok());
+    }
+}
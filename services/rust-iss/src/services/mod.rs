@@ -1,3 +1,12 @@
+pub mod altitude_service;
+pub mod cache_service;
+pub mod compaction_service;
+pub mod gapfill_service;
+pub mod iss_anomaly;
+pub mod iss_providers;
 pub mod iss_service;
+pub mod osdr_export;
 pub mod osdr_service;
-pub mod space_cache_service;
\ No newline at end of file
+pub mod region_service;
+pub mod retention_service;
+pub mod space_cache_service;
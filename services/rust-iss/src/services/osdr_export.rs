@@ -0,0 +1,505 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use parquet::basic::{Compression, LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::format::MicroSeconds;
+use parquet::schema::types::Type;
+use serde_json::{Map, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use tokio::sync::mpsc;
+
+/// Порог, после которого накопленный кусок CSV/NDJSON уходит клиенту.
+const CHUNK_BYTES: usize = 64 * 1024;
+/// Строк в одной row group Parquet — столько держим в памяти.
+const ROW_GROUP_ROWS: usize = 5_000;
+/// Защита от датасетов с тысячами уникальных ключей в raw.
+pub const MAX_RAW_COLUMNS: usize = 1_000;
+
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            "parquet" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+            Self::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+pub struct ExportQuery {
+    pub include_removed: bool,
+    /// Готовый ORDER BY, провалидированный так же, как в `/osdr/list`.
+    pub order_by: String,
+    /// Пути полей raw (`a.b.c`, точка и `\` внутри ключа экранируются `\`),
+    /// которые выгружаются колонками `raw.<путь>`.
+    pub raw_columns: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Int,
+    Text,
+    Time,
+}
+
+const BASE_COLUMNS: &[(&str, Kind)] = &[
+    ("id", Kind::Int),
+    ("dataset_id", Kind::Text),
+    ("title", Kind::Text),
+    ("status", Kind::Text),
+    ("description", Kind::Text),
+    ("organism", Kind::Text),
+    ("assay_types", Kind::Text),
+    ("mission", Kind::Text),
+    ("release_date", Kind::Time),
+    ("upstream_updated_at", Kind::Time),
+    ("updated_at", Kind::Time),
+    ("inserted_at", Kind::Time),
+    ("removed_at", Kind::Time),
+];
+
+enum Cell {
+    Null,
+    Int(i64),
+    Text(String),
+    List(Vec<String>),
+    Time(DateTime<Utc>),
+    Json(Value),
+}
+
+impl Cell {
+    fn text(&self) -> Option<String> {
+        match self {
+            Cell::Null => None,
+            Cell::Int(n) => Some(n.to_string()),
+            Cell::Text(s) => Some(s.clone()),
+            Cell::List(l) => Some(l.join("; ")),
+            Cell::Time(t) => Some(t.to_rfc3339()),
+            Cell::Json(Value::String(s)) => Some(s.clone()),
+            Cell::Json(v) => Some(v.to_string()),
+        }
+    }
+
+    fn json(self) -> Value {
+        match self {
+            Cell::Null => Value::Null,
+            Cell::Int(n) => n.into(),
+            Cell::Text(s) => s.into(),
+            Cell::List(l) => l.into(),
+            Cell::Time(t) => serde_json::json!(t),
+            Cell::Json(v) => v,
+        }
+    }
+}
+
+/// Все пути листьев raw по выгружаемым строкам; скаляр вместо объекта даёт путь "".
+/// Ключи экранируются так же, как в `escape_key`.
+pub async fn raw_columns(pool: &PgPool, include_removed: bool) -> anyhow::Result<Vec<String>> {
+    let rows = sqlx::query(
+        "WITH RECURSIVE f(path, val) AS (
+             SELECT NULL::text, raw FROM osdr_items WHERE $1 OR removed_at IS NULL
+             UNION ALL
+             SELECT COALESCE(f.path || '.', '') || replace(replace(e.key, '\\', '\\\\'), '.', '\\.'), e.value
+             FROM f, jsonb_each(CASE WHEN jsonb_typeof(f.val) = 'object' THEN f.val ELSE '{}'::jsonb END) e
+         )
+         SELECT DISTINCT COALESCE(path, '') AS path FROM f
+         WHERE jsonb_typeof(val) <> 'object'
+         ORDER BY 1 LIMIT $2"
+    )
+    .bind(include_removed).bind(MAX_RAW_COLUMNS as i64 + 1)
+    .fetch_all(pool).await?;
+    Ok(rows.into_iter().map(|r| r.get("path")).collect())
+}
+
+/// Выгрузка идёт в отдельной задаче: строки читаются курсором и кусками
+/// уходят в канал, так что в памяти не больше куска (или одной row group).
+pub fn spawn_export(pool: PgPool, format: ExportFormat, q: ExportQuery) -> mpsc::Receiver<anyhow::Result<Vec<u8>>> {
+    let (tx, rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        if let Err(e) = run_export(&pool, format, &q, &tx).await {
+            tracing::error!("osdr export: {e:?}");
+            let _ = tx.send(Err(e)).await;
+        }
+    });
+    rx
+}
+
+async fn run_export(pool: &PgPool, format: ExportFormat, q: &ExportQuery, tx: &mpsc::Sender<anyhow::Result<Vec<u8>>>) -> anyhow::Result<()> {
+    let mut header: Vec<String> = BASE_COLUMNS.iter().map(|(name, _)| name.to_string()).collect();
+    header.extend(q.raw_columns.iter().map(|p| if p.is_empty() { "raw".to_string() } else { format!("raw.{p}") }));
+    let mut encoder = Encoder::new(format, header)?;
+
+    let sql = format!(
+        "SELECT id, dataset_id, title, status, description, organism, assay_types, mission,
+                release_date, upstream_updated_at, updated_at, inserted_at, removed_at, raw
+         FROM osdr_items
+         WHERE $1 OR removed_at IS NULL
+         ORDER BY {}",
+        q.order_by
+    );
+    let mut rows = sqlx::query(&sql).bind(q.include_removed).fetch(pool);
+    while let Some(r) = rows.try_next().await? {
+        if let Some(chunk) = encoder.write(to_cells(&r, &q.raw_columns))? {
+            if tx.send(Ok(chunk)).await.is_err() {
+                return Ok(()); // клиент отключился
+            }
+        }
+    }
+    let _ = tx.send(Ok(encoder.finish()?)).await;
+    Ok(())
+}
+
+fn to_cells(r: &PgRow, raw_columns: &[String]) -> Vec<Cell> {
+    let text = |c: &str| r.get::<Option<String>, _>(c).map_or(Cell::Null, Cell::Text);
+    let time = |c: &str| r.get::<Option<DateTime<Utc>>, _>(c).map_or(Cell::Null, Cell::Time);
+    let mut cells = vec![
+        Cell::Int(r.get("id")),
+        text("dataset_id"),
+        text("title"),
+        text("status"),
+        text("description"),
+        text("organism"),
+        r.get::<Option<Vec<String>>, _>("assay_types").map_or(Cell::Null, Cell::List),
+        text("mission"),
+        time("release_date"),
+        time("upstream_updated_at"),
+        time("updated_at"),
+        Cell::Time(r.get("inserted_at")),
+        time("removed_at"),
+    ];
+    if !raw_columns.is_empty() {
+        let mut flat = Map::new();
+        flatten(&r.get::<Value, _>("raw"), "", &mut flat);
+        cells.extend(raw_columns.iter().map(|p| flat.remove(p).map_or(Cell::Null, Cell::Json)));
+    }
+    cells
+}
+
+// Те же правила, что в raw_columns: объекты раскрываются, остальное — лист.
+fn flatten(v: &Value, prefix: &str, out: &mut Map<String, Value>) {
+    match v {
+        Value::Object(m) => {
+            for (k, x) in m {
+                let k = escape_key(k);
+                let path = if prefix.is_empty() { k } else { format!("{prefix}.{k}") };
+                flatten(x, &path, out);
+            }
+        }
+        other => {
+            out.insert(prefix.to_string(), other.clone());
+        }
+    }
+}
+
+// Иначе поле `a.b` и вложенное {a: {b}} дали бы одну колонку.
+fn escape_key(k: &str) -> String {
+    k.replace('\\', "\\\\").replace('.', "\\.")
+}
+
+/// Буфер, общий для писателя и выгрузки: писатель дописывает, мы забираем куски.
+#[derive(Clone, Default)]
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuf {
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuf {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Encoder {
+    Csv(Box<csv::Writer<SharedBuf>>, SharedBuf),
+    Ndjson(Vec<String>, Vec<u8>),
+    Parquet(Box<ParquetEncoder>),
+}
+
+impl Encoder {
+    fn new(format: ExportFormat, header: Vec<String>) -> anyhow::Result<Self> {
+        Ok(match format {
+            ExportFormat::Csv => {
+                let buf = SharedBuf::default();
+                let mut w = Box::new(csv::Writer::from_writer(buf.clone()));
+                w.write_record(&header)?;
+                Encoder::Csv(w, buf)
+            }
+            ExportFormat::Ndjson => Encoder::Ndjson(header, Vec::new()),
+            ExportFormat::Parquet => Encoder::Parquet(Box::new(ParquetEncoder::new(&header)?)),
+        })
+    }
+
+    /// Пишет строку; возвращает кусок, если накопилось достаточно.
+    fn write(&mut self, cells: Vec<Cell>) -> anyhow::Result<Option<Vec<u8>>> {
+        match self {
+            Encoder::Csv(w, buf) => {
+                w.write_record(cells.iter().map(|c| c.text().unwrap_or_default()))?;
+                w.flush()?;
+                Ok((buf.len() >= CHUNK_BYTES).then(|| buf.take()))
+            }
+            Encoder::Ndjson(header, buf) => {
+                // вручную, чтобы ключи шли в порядке колонок, а не по алфавиту
+                buf.push(b'{');
+                for (i, (name, cell)) in header.iter().zip(cells).enumerate() {
+                    if i > 0 {
+                        buf.push(b',');
+                    }
+                    serde_json::to_writer(&mut *buf, name)?;
+                    buf.push(b':');
+                    serde_json::to_writer(&mut *buf, &cell.json())?;
+                }
+                buf.extend_from_slice(b"}\n");
+                Ok((buf.len() >= CHUNK_BYTES).then(|| std::mem::take(buf)))
+            }
+            Encoder::Parquet(p) => p.write(cells),
+        }
+    }
+
+    fn finish(self) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Encoder::Csv(mut w, buf) => {
+                w.flush()?;
+                Ok(buf.take())
+            }
+            Encoder::Ndjson(_, buf) => Ok(buf),
+            Encoder::Parquet(p) => p.finish(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ColumnBuf {
+    ints: Vec<i64>,
+    bytes: Vec<ByteArray>,
+    defs: Vec<i16>,
+}
+
+struct ParquetEncoder {
+    writer: SerializedFileWriter<SharedBuf>,
+    buf: SharedBuf,
+    kinds: Vec<Kind>,
+    columns: Vec<ColumnBuf>,
+    rows: usize,
+}
+
+impl ParquetEncoder {
+    fn new(header: &[String]) -> anyhow::Result<Self> {
+        let kinds: Vec<Kind> = header.iter().enumerate()
+            .map(|(i, _)| BASE_COLUMNS.get(i).map_or(Kind::Text, |(_, k)| *k))
+            .collect();
+        let fields = header.iter().zip(&kinds).map(|(name, kind)| {
+            let builder = match kind {
+                Kind::Int => Type::primitive_type_builder(name, PhysicalType::INT64),
+                Kind::Text => Type::primitive_type_builder(name, PhysicalType::BYTE_ARRAY)
+                    .with_logical_type(Some(LogicalType::String)),
+                Kind::Time => Type::primitive_type_builder(name, PhysicalType::INT64)
+                    .with_logical_type(Some(LogicalType::Timestamp {
+                        is_adjusted_to_u_t_c: true,
+                        unit: TimeUnit::MICROS(MicroSeconds {}),
+                    })),
+            };
+            builder.with_repetition(Repetition::OPTIONAL).build().map(Arc::new)
+        }).collect::<Result<Vec<_>, _>>()?;
+        let schema = Type::group_type_builder("osdr_items").with_fields(fields).build()?;
+        let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+
+        let buf = SharedBuf::default();
+        Ok(Self {
+            writer: SerializedFileWriter::new(buf.clone(), Arc::new(schema), Arc::new(props))?,
+            buf,
+            columns: kinds.iter().map(|_| ColumnBuf::default()).collect(),
+            kinds,
+            rows: 0,
+        })
+    }
+
+    fn write(&mut self, cells: Vec<Cell>) -> anyhow::Result<Option<Vec<u8>>> {
+        for ((cell, kind), col) in cells.into_iter().zip(&self.kinds).zip(&mut self.columns) {
+            let present = match (kind, cell) {
+                (_, Cell::Null) => false,
+                (Kind::Int, Cell::Int(n)) => { col.ints.push(n); true }
+                (Kind::Time, Cell::Time(t)) => { col.ints.push(t.timestamp_micros()); true }
+                (_, c) => match c.text() {
+                    Some(s) => { col.bytes.push(ByteArray::from(s.into_bytes())); true }
+                    None => false,
+                },
+            };
+            col.defs.push(present as i16);
+        }
+        self.rows += 1;
+        if self.rows < ROW_GROUP_ROWS {
+            return Ok(None);
+        }
+        self.flush_row_group()?;
+        Ok(Some(self.buf.take()))
+    }
+
+    fn flush_row_group(&mut self) -> anyhow::Result<()> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let mut rg = self.writer.next_row_group()?;
+        let mut i = 0;
+        while let Some(mut col) = rg.next_column()? {
+            let data = std::mem::take(&mut self.columns[i]);
+            match self.kinds[i] {
+                Kind::Int | Kind::Time => { col.typed::<Int64Type>().write_batch(&data.ints, Some(&data.defs), None)?; }
+                Kind::Text => { col.typed::<ByteArrayType>().write_batch(&data.bytes, Some(&data.defs), None)?; }
+            }
+            col.close()?;
+            i += 1;
+        }
+        rg.close()?;
+        self.rows = 0;
+        Ok(())
+    }
+
+    fn finish(mut self) -> anyhow::Result<Vec<u8>> {
+        self.flush_row_group()?;
+        self.writer.close()?;
+        Ok(self.buf.take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+
+    fn header() -> Vec<String> {
+        BASE_COLUMNS.iter().map(|(c, _)| c.to_string()).chain(["raw.extra.a".to_string()]).collect()
+    }
+
+    fn t() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+    }
+
+    fn row(id: i64, title: &str) -> Vec<Cell> {
+        let mut cells = vec![
+            Cell::Int(id), Cell::Text(format!("OSD-{id}")), Cell::Text(title.into()), Cell::Null, Cell::Null, Cell::Null,
+            Cell::List(vec!["RNA-Seq".into(), "ChIP".into()]), Cell::Null, Cell::Time(t()),
+        ];
+        cells.extend((0..4).map(|_| Cell::Null));
+        cells.push(Cell::Json(serde_json::json!([1, 3])));
+        cells
+    }
+
+    fn encode(format: ExportFormat, rows: Vec<Vec<Cell>>) -> Vec<u8> {
+        let mut enc = Encoder::new(format, header()).unwrap();
+        let mut out = Vec::new();
+        for cells in rows {
+            out.extend(enc.write(cells).unwrap().unwrap_or_default());
+        }
+        out.extend(enc.finish().unwrap());
+        out
+    }
+
+    #[test]
+    fn csv_header_and_quoting() {
+        let out = String::from_utf8(encode(ExportFormat::Csv, vec![row(1, "Mice, in \"space\"")])).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(lines.next().unwrap(), header().join(","));
+        assert_eq!(
+            lines.next().unwrap(),
+            "1,OSD-1,\"Mice, in \"\"space\"\"\",,,,RNA-Seq; ChIP,,2024-01-02T03:04:05+00:00,,,,,\"[1,3]\""
+        );
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn ndjson_keeps_column_order_and_types() {
+        let out = String::from_utf8(encode(ExportFormat::Ndjson, vec![row(1, "A"), row(2, "B")])).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("{\"id\":1,\"dataset_id\":\"OSD-1\",\"title\":\"A\",\"status\":null,"));
+        let v: Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(v["assay_types"], serde_json::json!(["RNA-Seq", "ChIP"]));
+        assert_eq!(v["release_date"], "2024-01-02T03:04:05Z");
+        assert_eq!(v["raw.extra.a"], serde_json::json!([1, 3]));
+        assert_eq!(v["removed_at"], Value::Null);
+    }
+
+    #[test]
+    fn parquet_round_trip() {
+        let out = encode(ExportFormat::Parquet, vec![row(1, "A"), row(2, "B")]);
+        assert!(out.starts_with(b"PAR1") && out.ends_with(b"PAR1"));
+        let path = std::env::temp_dir().join(format!("osdr_export_test_{}.parquet", std::process::id()));
+        std::fs::write(&path, &out).unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let meta = reader.metadata().file_metadata();
+        assert_eq!(meta.num_rows(), 2);
+        let names: Vec<String> = meta.schema().get_fields().iter().map(|f| f.name().to_string()).collect();
+        assert_eq!(names, header());
+
+        let rows: Vec<_> = reader.get_row_iter(None).unwrap().map(Result::unwrap).collect();
+        let cols: Vec<(&String, &Field)> = rows[1].get_column_iter().collect();
+        assert_eq!(cols[0].1, &Field::Long(2));
+        assert_eq!(cols[2].1, &Field::Str("B".into()));
+        assert_eq!(cols[3].1, &Field::Null);
+        assert_eq!(cols[6].1, &Field::Str("RNA-Seq; ChIP".into()));
+        assert_eq!(cols[8].1, &Field::TimestampMicros(t().timestamp_micros()));
+        assert_eq!(cols[13].1, &Field::Str("[1,3]".into()));
+    }
+
+    #[test]
+    fn flattens_nested_objects() {
+        let mut out = Map::new();
+        flatten(&serde_json::json!({ "a": { "b": 1, "c": { "d": [2] } }, "e": null }), "", &mut out);
+        assert_eq!(Value::Object(out), serde_json::json!({ "a.b": 1, "a.c.d": [2], "e": null }));
+        let mut out = Map::new();
+        flatten(&serde_json::json!("scalar"), "", &mut out);
+        assert_eq!(Value::Object(out), serde_json::json!({ "": "scalar" }));
+    }
+
+    #[test]
+    fn dotted_keys_do_not_collide() {
+        let mut out = Map::new();
+        flatten(&serde_json::json!({ "a.b": 1, "a": { "b": 2, "c\\.d": 3 } }), "", &mut out);
+        assert_eq!(Value::Object(out), serde_json::json!({ "a\\.b": 1, "a.b": 2, "a.c\\\\\\.d": 3 }));
+    }
+}
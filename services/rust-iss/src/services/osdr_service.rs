@@ -1,15 +1,152 @@
-use std::time::Duration;
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
 use reqwest;
+use serde::Serialize;
 use serde_json::Value;
-use sqlx::{PgPool, Row}; 
+use sqlx::{PgPool, Row};
+use tokio::task::JoinSet;
 
 use crate::app_state::AppState;
-use crate::utils::helpers::{s_pick, t_pick};
+use crate::services::cache_service::invalidate;
+use crate::services::iss_providers::check_fields;
+use crate::utils::diff::json_diff;
+use crate::utils::mapping::{self, FieldType, Mappings, Outcome, SourceMapping};
 
+#[derive(Serialize, Default)]
+pub struct OsdrSyncStats {
+    pub sync_id: i64,
+    pub written: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    /// Время записи в БД (без скачивания каталога).
+    pub load_ms: i64,
+    pub rows_per_sec: f64,
+    // списки id — в osdr_syncs, в ответе только счётчики
+    #[serde(skip)]
+    pub added_ids: Vec<String>,
+    #[serde(skip)]
+    pub modified_ids: Vec<String>,
+    #[serde(skip)]
+    pub removed_ids: Vec<String>,
+}
 
+/// Задание синхронизации: новое или уже идущее, к которому присоединился запрос.
+pub struct SyncJob {
+    pub id: i64,
+    pub coalesced: bool,
+}
 
-pub async fn fetch_and_store_osdr(st: &AppState) -> anyhow::Result<usize> {
+/// Запускает синхронизацию в фоне и сразу возвращает id задания;
+/// если синхронизация уже идёт, возвращает её.
+pub async fn start_osdr_sync(st: &AppState) -> anyhow::Result<SyncJob> {
+    // между неудачным захватом и поиском идущего задание могло закончиться
+    for _ in 0..3 {
+        if let Some(id) = claim_sync(st).await? {
+            let st = st.clone();
+            tokio::spawn(async move {
+                if let Err(e) = run_sync(&st, id).await {
+                    tracing::error!("osdr sync #{id} err {e:?}")
+                }
+            });
+            return Ok(SyncJob { id, coalesced: false });
+        }
+        let running = sqlx::query("SELECT id FROM osdr_syncs WHERE status = 'running'")
+            .fetch_optional(&st.pool).await?;
+        if let Some(r) = running {
+            return Ok(SyncJob { id: r.get("id"), coalesced: true });
+        }
+    }
+    anyhow::bail!("could not start OSDR sync")
+}
+
+/// Синхронизация в текущей задаче (фоновый цикл); `None`, если уже идёт другая.
+pub async fn fetch_and_store_osdr(st: &AppState) -> anyhow::Result<Option<OsdrSyncStats>> {
+    let Some(sync_id) = claim_sync(st).await? else {
+        tracing::info!("OSDR sync already running, skipping");
+        return Ok(None);
+    };
+    run_sync(st, sync_id).await.map(Some)
+}
+
+/// Задания running без heartbeat дольше `SYNC_STALE_SECONDS` уже никто не доделает
+/// (процесс упал или перезапущен). Задание другого живого экземпляра не трогаем.
+pub async fn fail_interrupted_syncs(pool: &PgPool) -> anyhow::Result<u64> {
+    Ok(sqlx::query(
+        "UPDATE osdr_syncs SET status = 'failed', error = 'interrupted: no heartbeat', finished_at = now()
+         WHERE status = 'running' AND heartbeat_at < now() - make_interval(secs => $1)"
+    ).bind(SYNC_STALE_SECONDS).execute(pool).await?.rows_affected())
+}
+
+// Уникальный индекс по running не даст завести второе задание;
+// брошенное задание сначала закрываем, иначе оно держало бы индекс вечно.
+async fn claim_sync(st: &AppState) -> anyhow::Result<Option<i64>> {
+    fail_interrupted_syncs(&st.pool).await?;
+    Ok(sqlx::query(
+        "INSERT INTO osdr_syncs(source_url, phase) VALUES($1, 'downloading')
+         ON CONFLICT (status) WHERE status = 'running' DO NOTHING
+         RETURNING id"
+    )
+    .bind(&st.nasa_url)
+    .fetch_optional(&st.pool).await?
+    .map(|r| r.get("id")))
+}
+
+// Прогресс пишется мимо транзакции синхронизации, иначе его никто не увидит.
+// Задание, которое уже закрыли как брошенное, дальше не идёт.
+async fn set_progress(pool: &PgPool, sync_id: i64, phase: &str, processed: usize, expected: usize) -> anyhow::Result<()> {
+    let updated = sqlx::query(
+        "UPDATE osdr_syncs SET phase = $2, processed = $3, expected = $4, heartbeat_at = now()
+         WHERE id = $1 AND status = 'running'"
+    )
+    .bind(sync_id).bind(phase).bind(processed as i32).bind(expected as i32)
+    .execute(pool).await?.rows_affected();
+    if updated == 0 {
+        anyhow::bail!("sync #{sync_id} is no longer running");
+    }
+    Ok(())
+}
+
+// Пульс между обновлениями прогресса: одно выражение (пометка удалённых, commit)
+// может идти дольше окна. Возвращается, только если задание уже не наше.
+async fn heartbeat(pool: &PgPool, sync_id: i64) {
+    let mut tick = tokio::time::interval(Duration::from_secs(SYNC_HEARTBEAT_SECONDS));
+    loop {
+        tick.tick().await;
+        let res = sqlx::query("UPDATE osdr_syncs SET heartbeat_at = now() WHERE id = $1 AND status = 'running'")
+            .bind(sync_id)
+            .execute(pool).await;
+        match res {
+            Ok(r) if r.rows_affected() == 0 => return,
+            Ok(_) => {}
+            Err(e) => tracing::warn!("osdr sync #{sync_id} heartbeat: {e}"),
+        }
+    }
+}
+
+/// Синхронизация osdr_items одной транзакцией: upsert по dataset_id,
+/// пропавшие из апстрима датасеты помечаются removed_at, а не удаляются.
+/// Каждый прогон (и неудачный тоже) записывается в osdr_syncs.
+async fn run_sync(st: &AppState, sync_id: i64) -> anyhow::Result<OsdrSyncStats> {
+    // если задание закрыли как брошенное, синхронизация обрывается и транзакция откатывается
+    let res = tokio::select! {
+        res = sync_catalog(st, sync_id) => res,
+        _ = heartbeat(&st.pool, sync_id) => Err(anyhow::anyhow!("sync #{sync_id} is no longer running")),
+    };
+    if let Err(e) = &res {
+        sqlx::query(
+            "UPDATE osdr_syncs SET status = 'failed', error = $2, finished_at = clock_timestamp()
+             WHERE id = $1 AND status = 'running'"
+        )
+            .bind(sync_id).bind(e.to_string())
+            .execute(&st.pool).await?;
+    }
+    res
+}
+
+async fn sync_catalog(st: &AppState, sync_id: i64) -> anyhow::Result<OsdrSyncStats> {
     let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build()?;
     let resp = client.get(&st.nasa_url).send().await?;
     
@@ -18,44 +155,477 @@ pub async fn fetch_and_store_osdr(st: &AppState) -> anyhow::Result<usize> {
     }
     
     let json: Value = resp.json().await?;
-    let mut written = 0usize;
-    
-    
-    sqlx::query("DELETE FROM osdr_items").execute(&st.pool).await?;
-    
-    
-    if let Some(obj) = json.as_object() {
-        for (dataset_id, item_data) in obj {
-           
-            let raw_value = if item_data.is_string() {
-                
-                let mut raw_obj = serde_json::Map::new();
-                raw_obj.insert("REST_URL".to_string(), item_data.clone());
-                Value::Object(raw_obj)
-            } else if item_data.is_object() {
-                
-                item_data.clone()
+    let Some(obj) = json.as_object() else {
+        anyhow::bail!("OSDR API returned unexpected format");
+    };
+    // пустой ответ скорее сбой апстрима, чем удаление всех датасетов
+    if obj.is_empty() {
+        anyhow::bail!("OSDR API returned no datasets");
+    }
+
+    let mut stats = OsdrSyncStats { sync_id, ..Default::default() };
+    let load_started = Instant::now();
+    let ids: Vec<&str> = obj.keys().map(String::as_str).collect();
+    let raws: Vec<Value> = obj.values().map(|item_data| {
+        if item_data.is_string() {
+            let mut raw_obj = serde_json::Map::new();
+            raw_obj.insert("REST_URL".to_string(), item_data.clone());
+            Value::Object(raw_obj)
+        } else {
+            item_data.clone()
+        }
+    }).collect();
+
+    set_progress(&st.pool, sync_id, "writing", 0, ids.len()).await?;
+    let mut tx = st.pool.begin().await?;
+    for (ids_batch, raws) in ids.chunks(SYNC_BATCH).zip(raws.chunks(SYNC_BATCH)) {
+        upsert_batch(&mut tx, ids_batch, raws, sync_id, &mut stats).await?;
+        set_progress(&st.pool, sync_id, "writing", stats.written, ids.len()).await?;
+    }
+    set_progress(&st.pool, sync_id, "removing", stats.written, ids.len()).await?;
+
+    stats.removed_ids = sqlx::query(
+        "WITH gone AS (
+             UPDATE osdr_items SET removed_at = now()
+             WHERE removed_at IS NULL AND dataset_id IS NOT NULL AND NOT (dataset_id = ANY($1))
+             RETURNING dataset_id
+         ), logged AS (
+             INSERT INTO osdr_item_history(dataset_id, change, sync_id) SELECT dataset_id, 'removed', $2 FROM gone
+         )
+         SELECT dataset_id FROM gone ORDER BY dataset_id"
+    )
+    .bind(&ids).bind(sync_id)
+    .fetch_all(&mut *tx).await?
+    .into_iter().map(|r| r.get("dataset_id")).collect();
+    stats.removed = stats.removed_ids.len();
+    set_progress(&st.pool, sync_id, "removing", stats.written, ids.len()).await?;
+
+    let load = load_started.elapsed();
+    stats.load_ms = load.as_millis() as i64;
+    stats.rows_per_sec = stats.written as f64 / load.as_secs_f64().max(1e-3);
+
+    // now() внутри транзакции — время её начала, поэтому clock_timestamp().
+    // Задание могли закрыть как брошенное, пока шла запись: тогда ничего не сохраняем.
+    let finished = sqlx::query(
+        "UPDATE osdr_syncs
+         SET status = 'ok', phase = 'done', finished_at = clock_timestamp(), total = $2, added = $3, modified = $4, removed = $5, unchanged = $6,
+             added_ids = $7, modified_ids = $8, removed_ids = $9, load_ms = $10, rows_per_sec = $11
+         WHERE id = $1 AND status = 'running'"
+    )
+    .bind(sync_id).bind(stats.written as i32).bind(stats.added_ids.len() as i32)
+    .bind(stats.modified_ids.len() as i32).bind(stats.removed as i32).bind(stats.unchanged as i32)
+    .bind(&stats.added_ids).bind(&stats.modified_ids).bind(&stats.removed_ids)
+    .bind(stats.load_ms).bind(stats.rows_per_sec)
+    .execute(&mut *tx).await?.rows_affected();
+    if finished == 0 {
+        tx.rollback().await?;
+        anyhow::bail!("sync #{sync_id} is no longer running, changes rolled back");
+    }
+    tx.commit().await?;
+    invalidate(&st.redis, &["osdr:*", "space:summary"]).await;
+
+    tracing::info!(
+        "OSDR: processed {} items ({} new, {} updated, {} removed) in {} ms, {:.0} rows/s",
+        stats.written, stats.inserted, stats.updated, stats.removed, stats.load_ms, stats.rows_per_sec
+    );
+    Ok(stats)
+}
+
+/// Пачка датасетов одним upsert'ом через unnest и одной вставкой в историю.
+/// CTE old видит состояние до INSERT — из него берётся diff; строка возвращается
+/// только если что-то поменялось (или датасет вернулся).
+async fn upsert_batch(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ids: &[&str],
+    raws: &[Value],
+    sync_id: i64,
+    stats: &mut OsdrSyncStats,
+) -> anyhow::Result<()> {
+    let rows = sqlx::query(
+        "WITH input AS (
+             SELECT * FROM unnest($1::text[], $2::jsonb[]) AS t(dataset_id, raw)
+         ), old AS (
+             SELECT o.dataset_id, o.raw AS old_raw, o.removed_at IS NOT NULL AS was_removed
+             FROM osdr_items o JOIN input i ON i.dataset_id = o.dataset_id
+         ), up AS (
+             INSERT INTO osdr_items(dataset_id, title, status, raw, updated_at)
+             SELECT dataset_id, NULL, NULL, raw, now() FROM input
+             ON CONFLICT (dataset_id) WHERE dataset_id IS NOT NULL DO UPDATE
+             SET raw = EXCLUDED.raw,
+                 updated_at = CASE WHEN osdr_items.raw IS DISTINCT FROM EXCLUDED.raw
+                                   THEN now() ELSE osdr_items.updated_at END,
+                 removed_at = NULL
+             WHERE osdr_items.raw IS DISTINCT FROM EXCLUDED.raw OR osdr_items.removed_at IS NOT NULL
+             RETURNING dataset_id, (xmax = 0) AS inserted
+         )
+         SELECT up.dataset_id, up.inserted, old.old_raw, old.was_removed
+         FROM up LEFT JOIN old ON old.dataset_id = up.dataset_id"
+    )
+    .bind(ids).bind(raws)
+    .fetch_all(&mut **tx).await?;
+
+    let new_raw: HashMap<&str, &Value> = ids.iter().copied().zip(raws).collect();
+    let mut h_ids = Vec::new();
+    let mut h_changes = Vec::new();
+    let mut h_diffs: Vec<Option<Value>> = Vec::new();
+    for r in &rows {
+        let dataset_id: String = r.get("dataset_id");
+        if r.get::<bool, _>("inserted") {
+            stats.inserted += 1;
+            stats.added_ids.push(dataset_id.clone());
+            h_changes.push("added");
+            h_diffs.push(None);
+        } else {
+            stats.updated += 1;
+            let old_raw: Option<Value> = r.get("old_raw");
+            let raw = new_raw.get(dataset_id.as_str()).copied().unwrap_or(&Value::Null);
+            let diff = json_diff(old_raw.as_ref().unwrap_or(&Value::Null), raw);
+            // вернувшийся датасет в отчёте считается добавленным
+            if r.get::<Option<bool>, _>("was_removed") == Some(true) {
+                stats.added_ids.push(dataset_id.clone());
+                h_changes.push("restored");
             } else {
-                
-                item_data.clone()
-            };
-            
-            sqlx::query(
-                "INSERT INTO osdr_items(dataset_id, title, status, raw)
-                 VALUES($1,$2,$3,$4)"
-            )
-            .bind(dataset_id)
-            .bind::<Option<String>>(None)  // title
-            .bind::<Option<String>>(None)  // status
-            .bind(raw_value)
-            .execute(&st.pool).await?;
-            
-            written += 1;
+                stats.modified_ids.push(dataset_id.clone());
+                h_changes.push("changed");
+            }
+            h_diffs.push((!diff.is_empty()).then_some(Value::Array(diff)));
         }
-    } else {
-        tracing::warn!("OSDR API returned unexpected format");
+        h_ids.push(dataset_id);
     }
-    
-    tracing::info!("OSDR: processed {} items", written);
-    Ok(written)
-}
\ No newline at end of file
+    stats.unchanged += ids.len() - rows.len();
+    stats.written += ids.len();
+
+    if !h_ids.is_empty() {
+        sqlx::query(
+            "INSERT INTO osdr_item_history(dataset_id, change, diff, sync_id)
+             SELECT dataset_id, change, diff, $4 FROM unnest($1::text[], $2::text[], $3::jsonb[]) AS t(dataset_id, change, diff)"
+        )
+        .bind(&h_ids).bind(&h_changes).bind(&h_diffs).bind(sync_id)
+        .execute(&mut **tx).await?;
+    }
+    Ok(())
+}
+
+/// Датасетов в одном upsert'е синхронизации.
+const SYNC_BATCH: usize = 1_000;
+
+/// Без heartbeat дольше — задание брошено. С запасом над таймаутом скачивания
+/// каталога (30 с) и несколькими пропущенными ударами пульса.
+const SYNC_STALE_SECONDS: f64 = 300.0;
+
+/// Как часто живое задание обновляет heartbeat_at помимо прогресса.
+const SYNC_HEARTBEAT_SECONDS: u64 = 30;
+
+/// Сколько датасетов обогащается за один проход.
+const ENRICH_BATCH: i64 = 200;
+
+/// Пауза после неудачного обогащения удваивается с каждой неудачей: 10 мин, 20 мин, ... до суток.
+const ENRICH_RETRY_MINUTES: i32 = 10;
+const ENRICH_RETRY_MAX_MINUTES: i32 = 24 * 60;
+
+#[derive(Serialize, Default)]
+pub struct OsdrEnrichStats {
+    pub enriched: usize,
+    pub failed: usize,
+    /// Без REST_URL по правилам osdr_catalog — обогащать нечем.
+    pub skipped: usize,
+}
+
+/// Метаданные датасета из его REST_URL.
+struct DatasetMeta {
+    title: Option<String>,
+    description: Option<String>,
+    status: Option<String>,
+    organism: Option<String>,
+    assay_types: Vec<String>,
+    mission: Option<String>,
+    release_date: Option<DateTime<Utc>>,
+    upstream_updated_at: Option<DateTime<Utc>>,
+    metadata: Value,
+}
+
+/// Ходит по REST_URL датасетов, у которых ещё нет метаданных, raw поменялся после
+/// последнего обогащения или метаданные старше `osdr_reenrich_days` (апстрим мог обновить
+/// их, не трогая каталог); не больше `concurrency` запросов сразу. Датасеты, на которых
+/// обогащение падало, ждут своей очереди с нарастающей паузой и не забивают пачку.
+pub async fn enrich_osdr(st: &AppState) -> anyhow::Result<OsdrEnrichStats> {
+    let rows = sqlx::query(
+        "SELECT id, dataset_id, raw
+         FROM osdr_items
+         WHERE removed_at IS NULL
+           AND (enriched_at IS NULL OR enriched_at < updated_at
+                OR ($2 > 0 AND enriched_at < now() - make_interval(days => $2)))
+           AND (enrich_failures = 0
+                OR enrich_attempted_at < now() - make_interval(mins =>
+                       LEAST($3 * power(2, LEAST(enrich_failures - 1, 16))::int, $4)))
+         ORDER BY enrich_failures, enriched_at NULLS FIRST, id
+         LIMIT $1"
+    )
+    .bind(ENRICH_BATCH).bind(st.osdr_reenrich_days as i32)
+    .bind(ENRICH_RETRY_MINUTES).bind(ENRICH_RETRY_MAX_MINUTES)
+    .fetch_all(&st.pool).await?;
+
+    let catalog = mapping::source("osdr_catalog");
+    let mut stats = OsdrEnrichStats::default();
+    let mut no_url = Vec::new();
+    let mut targets = Vec::new();
+    for r in rows {
+        let id: i64 = r.get("id");
+        let dataset_id: Option<String> = r.get("dataset_id");
+        let raw: Value = r.get("raw");
+        let root = catalog.resolve_root(&raw, &[("dataset_id", dataset_id.as_deref().unwrap_or_default())]);
+        match catalog.apply(root).text("rest_url") {
+            Some(url) => targets.push((id, dataset_id, url)),
+            None => no_url.push(id),
+        }
+    }
+    // чтобы не выбирать их снова, пока raw не изменится
+    if !no_url.is_empty() {
+        stats.skipped = sqlx::query("UPDATE osdr_items SET enriched_at = now() WHERE id = ANY($1)")
+            .bind(&no_url)
+            .execute(&st.pool).await?
+            .rows_affected() as usize;
+    }
+
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let mut pending = targets.into_iter();
+    let mut tasks = JoinSet::new();
+    loop {
+        while tasks.len() < st.osdr_enrich_concurrency.max(1) {
+            let Some((id, dataset_id, url)) = pending.next() else { break };
+            let (client, pool) = (client.clone(), st.pool.clone());
+            tasks.spawn(async move {
+                let res = enrich_one(&client, &pool, id, dataset_id.as_deref(), &url).await;
+                (id, dataset_id, res)
+            });
+        }
+        let Some(done) = tasks.join_next().await else { break };
+        match done? {
+            (_, _, Ok(())) => stats.enriched += 1,
+            (id, dataset_id, Err(e)) => {
+                tracing::warn!("OSDR enrich {}: {e}", dataset_id.unwrap_or_default());
+                sqlx::query(
+                    "UPDATE osdr_items
+                     SET enrich_error = $2, enrich_attempted_at = now(), enrich_failures = enrich_failures + 1
+                     WHERE id = $1"
+                ).bind(id).bind(e.to_string()).execute(&st.pool).await?;
+                stats.failed += 1;
+            }
+        }
+    }
+    if stats.enriched > 0 {
+        invalidate(&st.redis, &["osdr:*"]).await;
+    }
+    if stats.enriched + stats.failed + stats.skipped > 0 {
+        tracing::info!("OSDR: enriched {} datasets, {} failed, {} without REST_URL", stats.enriched, stats.failed, stats.skipped);
+    }
+    Ok(stats)
+}
+
+async fn enrich_one(client: &reqwest::Client, pool: &PgPool, id: i64, dataset_id: Option<&str>, url: &str) -> anyhow::Result<()> {
+    let resp = client.get(url).send().await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("HTTP {}", resp.status());
+    }
+    let m = parse_meta(&resp.json().await?, dataset_id);
+    let row = sqlx::query(
+        "WITH old AS (SELECT jsonb_build_object(
+                 'title', title, 'description', description, 'status', status, 'organism', organism,
+                 'assay_types', assay_types, 'mission', mission,
+                 'release_date', release_date, 'upstream_updated_at', upstream_updated_at) AS fields
+             FROM osdr_items WHERE id = $1)
+         UPDATE osdr_items
+         SET title = $2, description = $3, status = $4, organism = $5, assay_types = $6, mission = $7,
+             release_date = $8, upstream_updated_at = $9, metadata = $10, enriched_at = now(),
+             enrich_error = NULL, enrich_attempted_at = now(), enrich_failures = 0
+         WHERE id = $1
+         RETURNING dataset_id, (SELECT fields FROM old) AS old_fields,
+                   jsonb_build_object(
+                       'title', title, 'description', description, 'status', status, 'organism', organism,
+                       'assay_types', assay_types, 'mission', mission,
+                       'release_date', release_date, 'upstream_updated_at', upstream_updated_at) AS new_fields"
+    )
+    .bind(id).bind(m.title).bind(m.description).bind(m.status).bind(m.organism)
+    .bind(m.assay_types).bind(m.mission).bind(m.release_date).bind(m.upstream_updated_at)
+    .bind(m.metadata)
+    .fetch_one(pool).await?;
+
+    let diff = json_diff(&row.get::<Value, _>("old_fields"), &row.get::<Value, _>("new_fields"));
+    if let (Some(dataset_id), false) = (row.get::<Option<String>, _>("dataset_id"), diff.is_empty()) {
+        sqlx::query("INSERT INTO osdr_item_history(dataset_id, change, diff) VALUES($1, 'metadata', $2)")
+            .bind(dataset_id).bind(Value::Array(diff))
+            .execute(pool).await?;
+    }
+    Ok(())
+}
+
+// Ответ бывает вида {"OSD-1": {"metadata": {...}}}, {"metadata": {...}} или сразу метаданные.
+fn parse_meta(doc: &Value, dataset_id: Option<&str>) -> DatasetMeta {
+    let rules = mapping::source("osdr_metadata");
+    let m = rules.resolve_root(doc, &[("dataset_id", dataset_id.unwrap_or_default())]);
+    let rec = rules.apply(m);
+    DatasetMeta {
+        title: rec.text("title"),
+        description: rec.text("description"),
+        status: rec.text("status"),
+        organism: rec.text("organism"),
+        assay_types: rec.list("assay_types"),
+        mission: rec.text("mission"),
+        release_date: rec.time("release_date"),
+        upstream_updated_at: rec.time("upstream_updated_at"),
+        metadata: m.clone(),
+    }
+}
+
+/// Поля, которые правила источников обязаны давать (см. data/field_mappings.json).
+pub const CATALOG_FIELDS: &[(&str, FieldType)] = &[("rest_url", FieldType::String)];
+pub const METADATA_FIELDS: &[(&str, FieldType)] = &[
+    ("title", FieldType::String),
+    ("description", FieldType::String),
+    ("status", FieldType::String),
+    ("organism", FieldType::String),
+    ("assay_types", FieldType::List),
+    ("mission", FieldType::String),
+    ("release_date", FieldType::Timestamp),
+    ("upstream_updated_at", FieldType::Timestamp),
+];
+
+#[derive(Serialize)]
+pub struct PathHits {
+    pub path: String,
+    pub hits: usize,
+}
+
+#[derive(Serialize)]
+pub struct Unparsable {
+    pub dataset_id: Option<String>,
+    /// Строка iss_fetch_log — для правил провайдеров ISS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch_id: Option<i64>,
+    pub path: String,
+    pub value: Value,
+}
+
+#[derive(Serialize)]
+pub struct RuleReport {
+    pub source: String,
+    pub field: String,
+    pub rows: usize,
+    pub matched: usize,
+    pub missing: usize,
+    pub unparsable: usize,
+    /// Сколько раз сработал каждый путь — нулевые можно убирать.
+    pub paths: Vec<PathHits>,
+    pub unparsable_examples: Vec<Unparsable>,
+    /// Правило ни разу не сработало или встретило значения, которые не приводятся к типу.
+    pub failing: bool,
+}
+
+#[derive(Serialize, Default)]
+pub struct MappingReport {
+    pub config_errors: Vec<String>,
+    pub rules: Vec<RuleReport>,
+}
+
+impl MappingReport {
+    pub fn failing(&self) -> usize {
+        self.config_errors.len() + self.rules.iter().filter(|r| r.failing).count()
+    }
+}
+
+/// Сколько последних ответов провайдера ISS берётся для проверки его правил.
+const MAPPING_SAMPLE_ROWS: i64 = 500;
+
+/// Документ, на котором проверяются правила, и откуда он взят.
+struct Sample {
+    dataset_id: Option<String>,
+    fetch_id: Option<i64>,
+    doc: Value,
+}
+
+/// Прогоняет правила по тому, что уже лежит в базе:
+/// osdr_catalog — по raw osdr_items, osdr_metadata — по сохранённым metadata,
+/// `iss_<провайдер>` — по последним `MAPPING_SAMPLE_ROWS` ответам провайдера в iss_fetch_log.
+pub async fn validate_mappings(pool: &PgPool, mappings: &Mappings) -> anyhow::Result<MappingReport> {
+    let mut report = MappingReport::default();
+    for (source, rules) in mappings.iter().filter(|(s, _)| s.starts_with("iss_")) {
+        report.config_errors.extend(check_fields(rules).into_iter().map(|e| format!("{source}.{e}")));
+
+        let samples: Vec<Sample> = sqlx::query(
+            "SELECT id, payload FROM iss_fetch_log WHERE provider = $1 ORDER BY id DESC LIMIT $2"
+        )
+        .bind(source.trim_start_matches("iss_")).bind(MAPPING_SAMPLE_ROWS)
+        .fetch_all(pool).await?
+        .into_iter()
+        .map(|r| Sample { dataset_id: None, fetch_id: Some(r.get("id")), doc: r.get("payload") })
+        .collect();
+        report.rules.extend(check_rules(source, rules, &samples, true));
+    }
+    // metadata хранится уже после root, повторно его не ищем
+    let sources = [
+        ("osdr_catalog", CATALOG_FIELDS, "raw", true),
+        ("osdr_metadata", METADATA_FIELDS, "metadata", false),
+    ];
+    for (source, targets, column, rooted) in sources {
+        let Some(rules) = mappings.get(source) else {
+            report.config_errors.push(format!("{source}: no rules"));
+            continue;
+        };
+        report.config_errors.extend(rules.check(targets).into_iter().map(|e| format!("{source}.{e}")));
+
+        let samples: Vec<Sample> = sqlx::query(&format!(
+            "SELECT dataset_id, {column} AS doc FROM osdr_items
+             WHERE removed_at IS NULL AND {column} IS NOT NULL ORDER BY id"
+        ))
+        .fetch_all(pool).await?
+        .into_iter()
+        .map(|r| Sample { dataset_id: r.get("dataset_id"), fetch_id: None, doc: r.get("doc") })
+        .collect();
+        report.rules.extend(check_rules(source, rules, &samples, rooted));
+    }
+    Ok(report)
+}
+
+fn check_rules(source: &str, rules: &SourceMapping, samples: &[Sample], rooted: bool) -> Vec<RuleReport> {
+    rules.fields.iter().map(|(field, rule)| {
+        let mut r = RuleReport {
+            source: source.to_string(),
+            field: field.clone(),
+            rows: samples.len(),
+            matched: 0,
+            missing: 0,
+            unparsable: 0,
+            paths: rule.paths.iter().map(|p| PathHits { path: p.clone(), hits: 0 }).collect(),
+            unparsable_examples: Vec::new(),
+            failing: false,
+        };
+        for s in samples {
+            let rec = if rooted {
+                rules.resolve_root(&s.doc, &[("dataset_id", s.dataset_id.as_deref().unwrap_or_default())])
+            } else {
+                &s.doc
+            };
+            match rule.eval(rec) {
+                Outcome::Matched(i, _) => {
+                    r.matched += 1;
+                    r.paths[i].hits += 1;
+                }
+                Outcome::Missing => r.missing += 1,
+                Outcome::Unparsable(i) => {
+                    r.unparsable += 1;
+                    if r.unparsable_examples.len() < 5 {
+                        let value = rec.pointer(&rule.paths[i]).cloned().unwrap_or(Value::Null);
+                        r.unparsable_examples.push(Unparsable {
+                            dataset_id: s.dataset_id.clone(),
+                            fetch_id: s.fetch_id,
+                            path: rule.paths[i].clone(),
+                            value,
+                        });
+                    }
+                }
+            }
+        }
+        r.failing = r.rows > 0 && (r.matched == 0 || r.unparsable > 0);
+        r
+    }).collect()
+}
@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+
+use crate::utils::geo::{contains, parse_polygons};
+
+/// Сверяет новую позицию ISS со всеми геозонами и пишет события входа/выхода.
+pub async fn evaluate_regions(
+    pool: &PgPool,
+    fetch_id: i64,
+    at: DateTime<Utc>,
+    lat: f64,
+    lon: f64,
+) -> anyhow::Result<usize> {
+    let mut tx = pool.begin().await?;
+    // FOR UPDATE: фоновый цикл и /fetch не должны задвоить событие
+    let rows = sqlx::query("SELECT id, name, geometry, inside FROM iss_regions ORDER BY id FOR UPDATE")
+        .fetch_all(&mut *tx).await?;
+
+    let mut events = 0usize;
+    for r in rows {
+        let id: i64 = r.get("id");
+        let geometry: Value = r.get("geometry");
+        let was_inside: bool = r.get("inside");
+        let polygons = match parse_polygons(&geometry) {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::warn!("region {id} has invalid geometry: {e}");
+                continue;
+            }
+        };
+        let inside = contains(&polygons, lon, lat);
+        if inside == was_inside {
+            continue;
+        }
+
+        let event = if inside { "enter" } else { "exit" };
+        sqlx::query(
+            "INSERT INTO iss_region_events(region_id, fetch_id, event, occurred_at, lat, lon)
+             VALUES($1,$2,$3,$4,$5,$6)"
+        )
+        .bind(id).bind(fetch_id).bind(event).bind(at).bind(lat).bind(lon)
+        .execute(&mut *tx).await?;
+        sqlx::query("UPDATE iss_regions SET inside = $2 WHERE id = $1")
+            .bind(id).bind(inside)
+            .execute(&mut *tx).await?;
+
+        tracing::info!("ISS {} region {} ({})", event, id, r.get::<String, _>("name"));
+        events += 1;
+    }
+    tx.commit().await?;
+    Ok(events)
+}
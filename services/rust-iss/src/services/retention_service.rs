@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::app_state::AppState;
+use crate::services::cache_service::invalidate;
+
+/// Политики по умолчанию, вшитые в бинарник.
+const BUNDLED: &str = include_str!("../../data/retention.json");
+
+/// Строк за один DELETE: короткие транзакции не держат блокировки долго.
+const BATCH: i64 = 1000;
+
+/// Таблицы под политиками: (таблица, колонка времени, колонка источника, группа внутри
+/// источника). keep_last и keep_daily считаются по каждой паре (источник, группа):
+/// иначе один спутник провайдера вытеснял бы строки остальных.
+/// Удаление из iss_fetch_log каскадом удаляет позиции из iss_positions.
+const TABLES: &[(&str, &str, &str, &str)] = &[
+    ("space_cache", "fetched_at", "source", "NULL::int"),
+    ("iss_fetch_log", "fetched_at", "provider", "satellite_id"),
+];
+
+/// Политики по таблицам (`space_cache`, `iss_fetch_log`).
+pub type RetentionConfig = BTreeMap<String, TablePolicy>;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TablePolicy {
+    /// Для источников, у которых нет своей политики; нет — такие строки не трогаем.
+    pub default: Option<Policy>,
+    #[serde(default)]
+    pub sources: BTreeMap<String, Policy>,
+}
+
+/// Строка остаётся, если её оставляет хотя бы одно правило.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Последние N строк источника.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_last: Option<i64>,
+    /// Все строки моложе N суток.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_within_days: Option<i32>,
+    /// Последняя строка каждых суток (UTC) моложе N суток.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_daily_days: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct PolicyReport {
+    pub table: &'static str,
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub satellite_id: Option<i32>,
+    pub policy: Policy,
+    /// Удалено строк (в dry-run — сколько было бы удалено).
+    pub deleted: u64,
+    pub batches: usize,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Default)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub deleted: u64,
+    pub policies: Vec<PolicyReport>,
+}
+
+/// Вшитые политики; `RETENTION_POLICIES_PATH` подменяет их по таблицам.
+/// Ошибки не глотаются: кривая политика может удалить лишнее.
+pub fn load_retention_config() -> anyhow::Result<RetentionConfig> {
+    let mut cfg: RetentionConfig = serde_json::from_str(BUNDLED).expect("bundled retention.json is valid");
+    if let Ok(path) = std::env::var("RETENTION_POLICIES_PATH") {
+        let text = std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+        let custom: RetentionConfig = serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+        cfg.extend(custom);
+    }
+    let errors = check(&cfg);
+    if !errors.is_empty() {
+        anyhow::bail!("invalid retention policies: {}", errors.join("; "));
+    }
+    Ok(cfg)
+}
+
+fn check(cfg: &RetentionConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (table, tp) in cfg {
+        if !TABLES.iter().any(|(t, _, _, _)| t == table) {
+            errors.push(format!("{table}: unknown table"));
+        }
+        let named = tp.sources.iter().map(|(s, p)| (s.as_str(), p));
+        for (source, p) in tp.default.iter().map(|p| ("default", p)).chain(named) {
+            if p.keep_last.is_none() && p.keep_within_days.is_none() && p.keep_daily_days.is_none() {
+                errors.push(format!("{table}/{source}: no keep rule, every row would be deleted"));
+            }
+            if p.keep_last.is_some_and(|n| n < 1)
+                || p.keep_within_days.is_some_and(|n| n < 1)
+                || p.keep_daily_days.is_some_and(|n| n < 1)
+            {
+                errors.push(format!("{table}/{source}: keep values must be positive"));
+            }
+        }
+    }
+    errors
+}
+
+/// Политики iss_fetch_log, которые могут удалить сырые строки раньше компактизации:
+/// компактизация сворачивает сутки целиком, поэтому keep_within_days должен быть
+/// хотя бы на сутки больше `compact_after_days`.
+pub fn compaction_conflicts(cfg: &RetentionConfig, compact_after_days: i64) -> Vec<String> {
+    let Some(tp) = cfg.get("iss_fetch_log") else { return Vec::new() };
+    let named = tp.sources.iter().map(|(s, p)| (s.as_str(), p));
+    tp.default.iter().map(|p| ("default", p)).chain(named)
+        .filter(|(_, p)| p.keep_within_days.is_none_or(|d| (d as i64) <= compact_after_days))
+        .map(|(s, _)| s.to_string())
+        .collect()
+}
+
+/// Строки пары (источник `$1`, группа `$2`), которые не оставляет ни одно правило.
+fn victims_sql(table: &str, ts: &str, src: &str, part: &str) -> String {
+    format!(
+        "WITH ranked AS (
+             SELECT id, {ts} AS ts,
+                    row_number() OVER (PARTITION BY {src}, {part} ORDER BY {ts} DESC, id DESC) AS rn,
+                    row_number() OVER (PARTITION BY {src}, {part}, date_trunc('day', {ts}, 'UTC') ORDER BY {ts} DESC, id DESC) AS day_rn
+             FROM {table}
+             WHERE {src} IS NOT DISTINCT FROM $1 AND {part} IS NOT DISTINCT FROM $2
+         )
+         SELECT id, ts FROM ranked
+         WHERE NOT (coalesce(rn <= $3, false)
+                 OR coalesce(ts >= now() - make_interval(days => $4), false)
+                 OR coalesce(day_rn = 1 AND ts >= now() - make_interval(days => $5), false))"
+    )
+}
+
+/// Применяет политики ко всем источникам таблиц. В dry-run только считает,
+/// иначе удаляет пачками по `BATCH` строк, каждая пачка — отдельная транзакция.
+pub async fn run_retention(st: &AppState, dry_run: bool) -> anyhow::Result<RetentionReport> {
+    let (pool, cfg) = (&st.pool, &st.retention);
+    let mut report = RetentionReport { dry_run, ..Default::default() };
+    for &(table, ts, src, part) in TABLES {
+        let Some(tp) = cfg.get(table) else { continue };
+        let groups: Vec<(Option<String>, Option<i32>)> = sqlx::query(&format!("SELECT DISTINCT {src} AS s, {part} AS g FROM {table}"))
+            .fetch_all(pool).await?
+            .iter().map(|r| (r.get("s"), r.get("g"))).collect();
+        let victims = victims_sql(table, ts, src, part);
+
+        for (source, satellite_id) in groups {
+            let named = source.as_deref().and_then(|s| tp.sources.get(s));
+            let Some(policy) = named.or(tp.default.as_ref()) else { continue };
+            let mut pr = PolicyReport {
+                table, source, satellite_id, policy: policy.clone(),
+                deleted: 0, batches: 0, oldest: None, newest: None,
+            };
+
+            let sql = if dry_run {
+                format!("SELECT count(*) AS n, min(ts) AS oldest, max(ts) AS newest FROM ({victims}) v")
+            } else {
+                format!(
+                    "WITH v AS ({victims} ORDER BY ts LIMIT $6),
+                          d AS (DELETE FROM {table} t USING v WHERE t.id = v.id RETURNING v.ts)
+                     SELECT count(*) AS n, min(ts) AS oldest, max(ts) AS newest FROM d"
+                )
+            };
+            loop {
+                let mut q = sqlx::query(&sql)
+                    .bind(&pr.source)
+                    .bind(pr.satellite_id)
+                    .bind(policy.keep_last)
+                    .bind(policy.keep_within_days)
+                    .bind(policy.keep_daily_days);
+                if !dry_run {
+                    q = q.bind(BATCH);
+                }
+                let row = q.fetch_one(pool).await?;
+                let n = row.get::<i64, _>("n") as u64;
+                let oldest: Option<DateTime<Utc>> = row.get("oldest");
+                let newest: Option<DateTime<Utc>> = row.get("newest");
+                if n > 0 {
+                    pr.deleted += n;
+                    pr.batches += 1;
+                    // пачки идут от старых к новым
+                    pr.oldest = pr.oldest.or(oldest);
+                    pr.newest = pr.newest.max(newest);
+                }
+                if dry_run || (n as i64) < BATCH {
+                    break;
+                }
+                // дать место остальным запросам между пачками
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+
+            report.deleted += pr.deleted;
+            if pr.deleted > 0 {
+                report.policies.push(pr);
+            }
+        }
+    }
+    // политика без keep_last может удалить и последнюю строку источника
+    if !dry_run && report.deleted > 0 {
+        invalidate(&st.redis, &["space:*", "iss:last:*"]).await;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg(json: &str) -> RetentionConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn bundled_policies_are_valid() {
+        let bundled = cfg(BUNDLED);
+        assert!(check(&bundled).is_empty());
+        assert!(compaction_conflicts(&bundled, 30).is_empty());
+    }
+
+    #[test]
+    fn raw_iss_rows_must_outlive_compaction_by_a_day() {
+        let c = cfg(r#"{"iss_fetch_log": {
+            "default": { "keep_within_days": 31 },
+            "sources": { "a": { "keep_within_days": 30 }, "b": { "keep_last": 100 } } }}"#);
+        assert_eq!(compaction_conflicts(&c, 30), vec!["a", "b"]);
+        assert_eq!(compaction_conflicts(&c, 31), vec!["default", "a", "b"]);
+        assert!(compaction_conflicts(&cfg(r#"{"space_cache": {"default": {"keep_last": 1}}}"#), 30).is_empty());
+    }
+
+    #[test]
+    fn rejects_policies_that_delete_everything() {
+        let errors = check(&cfg(r#"{"space_cache": {"default": {}}, "nope": {"sources": {"x": {"keep_last": 0}}}}"#));
+        assert_eq!(errors, vec![
+            "nope: unknown table",
+            "nope/x: keep values must be positive",
+            "space_cache/default: no keep rule, every row would be deleted",
+        ]);
+    }
+}
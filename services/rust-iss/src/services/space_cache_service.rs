@@ -1,16 +1,62 @@
 use std::time::Duration;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use reqwest;
 use serde_json::Value;
 
 use crate::app_state::AppState;
+use crate::services::cache_service::invalidate;
 
-async fn write_cache(pool: &sqlx::PgPool, source: &str, payload: Value) -> anyhow::Result<()> {
+async fn write_cache(st: &AppState, source: &str, payload: Value) -> anyhow::Result<()> {
     sqlx::query("INSERT INTO space_cache(source, payload) VALUES ($1,$2)")
-        .bind(source).bind(payload).execute(pool).await?;
+        .bind(source).bind(payload).execute(&st.pool).await?;
+    invalidate(&st.redis, &["space:summary", &format!("space:latest:{source}")]).await;
     Ok(())
 }
 
+/// Источник space_cache: имя в URL, описание, фоновый опрос.
+pub struct SpaceSource {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+pub const SPACE_SOURCES: &[SpaceSource] = &[
+    SpaceSource { name: "apod", description: "NASA Astronomy Picture of the Day" },
+    SpaceSource { name: "neo", description: "NASA NeoWs: near-Earth objects approaching in the last days" },
+    SpaceSource { name: "flr", description: "NASA DONKI: solar flares" },
+    SpaceSource { name: "cme", description: "NASA DONKI: coronal mass ejections" },
+    SpaceSource { name: "spacex", description: "SpaceX: next launch" },
+];
+
+/// Запись считается устаревшей, если пропущено больше одного цикла опроса.
+const STALE_FACTOR: i64 = 2;
+
+pub fn find_source(name: &str) -> Option<&'static SpaceSource> {
+    SPACE_SOURCES.iter().find(|s| s.name == name)
+}
+
+impl SpaceSource {
+    /// Период фонового опроса, секунды.
+    pub fn interval(&self, st: &AppState) -> u64 {
+        match self.name {
+            "apod" => st.every_apod,
+            "neo" => st.every_neo,
+            "flr" | "cme" => st.every_donki,
+            _ => st.every_spacex,
+        }
+    }
+
+    /// `age_seconds` и `stale` для записи, полученной в `fetched_at`; без записи — устарело.
+    pub fn freshness(&self, st: &AppState, fetched_at: Option<DateTime<Utc>>) -> (Option<i64>, bool) {
+        let age = fetched_at.map(|t| (Utc::now() - t).num_seconds().max(0));
+        (age, age.is_none_or(|a| a > self.interval(st) as i64 * STALE_FACTOR))
+    }
+
+    /// Момент, после которого запись, полученная в `fetched_at`, считается устаревшей.
+    pub fn stale_at(&self, st: &AppState, fetched_at: DateTime<Utc>) -> DateTime<Utc> {
+        fetched_at + chrono::Duration::seconds(self.interval(st) as i64 * STALE_FACTOR)
+    }
+}
+
 fn last_days(n: i64) -> (String,String) {
     let to = Utc::now().date_naive();
     let from = to - chrono::Days::new(n as u64);
@@ -43,12 +89,11 @@ pub async fn fetch_apod(st: &AppState) -> anyhow::Result<()> {
     }
 
     let json: Value = serde_json::from_str(&text)
-        .map_err(|e| {
+        .inspect_err(|_| {
             tracing::error!("APOD invalid json body={}", text);
-            e
         })?;
 
-    write_cache(&st.pool, "apod", json).await
+    write_cache(st, "apod", json).await
 }
 
 
@@ -88,7 +133,7 @@ pub async fn fetch_neo_feed(st: &AppState) -> anyhow::Result<()> {
     match serde_json::from_str::<Value>(&text) {
         Ok(json) => {
             tracing::info!("NEO: successfully fetched");
-            write_cache(&st.pool, "neo", json).await
+            write_cache(st, "neo", json).await
         },
         Err(e) => {
             tracing::error!("NEO invalid json: {}, body={}", e, &text[..text.len().min(500)]);
@@ -98,13 +143,6 @@ pub async fn fetch_neo_feed(st: &AppState) -> anyhow::Result<()> {
 }
 
 
-pub async fn fetch_donki(st: &AppState) -> anyhow::Result<()> {
-    let _ = fetch_donki_flr(st).await;
-    let _ = fetch_donki_cme(st).await;
-    Ok(())
-}
-
-
 pub async fn fetch_donki_flr(st: &AppState) -> anyhow::Result<()> {
     let (from,to) = last_days(5);
     let url = "https://api.nasa.gov/DONKI/FLR";
@@ -133,7 +171,7 @@ pub async fn fetch_donki_flr(st: &AppState) -> anyhow::Result<()> {
     match serde_json::from_str::<Value>(&text) {
         Ok(json) => {
             tracing::info!("DONKI FLR: successfully fetched");
-            write_cache(&st.pool, "flr", json).await
+            write_cache(st, "flr", json).await
         },
         Err(e) => {
             tracing::error!("DONKI FLR invalid json: {}, body={}", e, &text[..text.len().min(500)]);
@@ -171,7 +209,7 @@ pub async fn fetch_donki_cme(st: &AppState) -> anyhow::Result<()> {
     match serde_json::from_str::<Value>(&text) {
         Ok(json) => {
             tracing::info!("DONKI CME: successfully fetched");
-            write_cache(&st.pool, "cme", json).await
+            write_cache(st, "cme", json).await
         },
         Err(e) => {
             tracing::error!("DONKI CME invalid json: {}, body={}", e, &text[..text.len().min(500)]);
@@ -200,7 +238,7 @@ pub async fn fetch_spacex_next(st: &AppState) -> anyhow::Result<()> {
     match serde_json::from_str::<Value>(&text) {
         Ok(json) => {
             tracing::info!("SpaceX: successfully fetched");
-            write_cache(&st.pool, "spacex", json).await
+            write_cache(st, "spacex", json).await
         },
         Err(e) => {
             tracing::error!("SpaceX invalid json: {}, body={}", e, &text[..text.len().min(500)]);
@@ -0,0 +1,137 @@
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::Value;
+
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Тело вместе с валидаторами: в таком виде ответ и лежит в кэше,
+/// чтобы 304 можно было отдать без похода в базу.
+pub fn versioned(body: Value, etag: String, last_modified: Option<DateTime<Utc>>) -> Value {
+    serde_json::json!({ "etag": etag, "last_modified": last_modified, "body": body })
+}
+
+/// 304, если клиент прислал совпадающий If-None-Match (или, без него,
+/// If-Modified-Since не раньше Last-Modified), иначе тело с ETag/Last-Modified.
+pub fn respond(headers: &HeaderMap, v: Value) -> Response {
+    // запись из кэша старого формата — просто тело без валидаторов
+    let Some(etag) = v.get("etag").and_then(Value::as_str).map(quote) else {
+        return Json(v).into_response();
+    };
+    let last_modified = v.get("last_modified")
+        .and_then(|t| serde_json::from_value::<DateTime<Utc>>(t.clone()).ok());
+
+    let not_modified = match headers.get(header::IF_NONE_MATCH).and_then(|h| h.to_str().ok()) {
+        // If-None-Match сравнивается слабо: W/ не учитывается
+        Some(inm) => inm.split(',').map(|t| t.trim().trim_start_matches("W/"))
+            .any(|t| t == etag.trim_start_matches("W/") || t == "*"),
+        None => match (last_modified, headers.get(header::IF_MODIFIED_SINCE).and_then(|h| h.to_str().ok())) {
+            (Some(lm), Some(ims)) => NaiveDateTime::parse_from_str(ims, HTTP_DATE)
+                .is_ok_and(|ims| lm.timestamp() <= ims.and_utc().timestamp()),
+            _ => false,
+        },
+    };
+
+    let mut validators = vec![(header::ETAG, etag), (header::CACHE_CONTROL, "no-cache".to_string())];
+    if let Some(lm) = last_modified {
+        validators.push((header::LAST_MODIFIED, lm.format(HTTP_DATE).to_string()));
+    }
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, validators_map(validators)).into_response();
+    }
+    let body = v.get("body").cloned().unwrap_or(Value::Null);
+    (validators_map(validators), Json(body)).into_response()
+}
+
+/// `W/` в начале — слабый ETag: представление может отличаться в мелочах.
+fn quote(e: &str) -> String {
+    match e.strip_prefix("W/") {
+        Some(weak) => format!("W/\"{weak}\""),
+        None => format!("\"{e}\""),
+    }
+}
+
+fn validators_map(validators: Vec<(header::HeaderName, String)>) -> HeaderMap {
+    validators.into_iter()
+        .filter_map(|(k, v)| Some((k, v.parse().ok()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn lm() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap() + chrono::Duration::milliseconds(500)
+    }
+
+    fn entry(etag: &str) -> Value {
+        versioned(serde_json::json!({ "ok": true }), etag.to_string(), Some(lm()))
+    }
+
+    fn status(etag: &str, h: &[(header::HeaderName, &str)]) -> StatusCode {
+        let headers: HeaderMap = h.iter().map(|(k, v)| (k.clone(), v.parse().unwrap())).collect();
+        respond(&headers, entry(etag)).status()
+    }
+
+    #[test]
+    fn if_none_match() {
+        let inm = header::IF_NONE_MATCH;
+        assert_eq!(status("abc", &[(inm.clone(), "\"abc\"")]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status("abc", &[(inm.clone(), "\"xyz\"")]), StatusCode::OK);
+        // сравнение слабое: W/ не важен ни у нас, ни у клиента
+        assert_eq!(status("W/abc", &[(inm.clone(), "W/\"abc\"")]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status("W/abc", &[(inm.clone(), "\"abc\"")]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status("abc", &[(inm.clone(), "W/\"abc\"")]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status("abc", &[(inm.clone(), "\"x\", W/\"abc\" ,\"y\"")]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status("abc", &[(inm.clone(), "\"x\", \"y\"")]), StatusCode::OK);
+        assert_eq!(status("abc", &[(inm, "*")]), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn if_modified_since_second_precision() {
+        let ims = header::IF_MODIFIED_SINCE;
+        // Last-Modified 12:00:00.5 — в заголовке доли секунды теряются
+        assert_eq!(status("abc", &[(ims.clone(), "Mon, 19 Oct 2026 12:00:00 GMT")]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status("abc", &[(ims.clone(), "Mon, 19 Oct 2026 12:00:01 GMT")]), StatusCode::NOT_MODIFIED);
+        assert_eq!(status("abc", &[(ims.clone(), "Mon, 19 Oct 2026 11:59:59 GMT")]), StatusCode::OK);
+        assert_eq!(status("abc", &[(ims, "yesterday")]), StatusCode::OK);
+    }
+
+    #[test]
+    fn if_none_match_wins_over_if_modified_since() {
+        let fresh = (header::IF_MODIFIED_SINCE, "Mon, 19 Oct 2026 13:00:00 GMT");
+        let stale = (header::IF_MODIFIED_SINCE, "Mon, 19 Oct 2026 11:00:00 GMT");
+        assert_eq!(status("abc", &[(header::IF_NONE_MATCH, "\"xyz\""), fresh]), StatusCode::OK);
+        assert_eq!(status("abc", &[(header::IF_NONE_MATCH, "\"abc\""), stale]), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn not_modified_keeps_validators_without_body() {
+        let headers: HeaderMap = [(header::IF_NONE_MATCH, "W/\"abc\"".parse().unwrap())].into_iter().collect();
+        let resp = respond(&headers, entry("W/abc"));
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers()[header::ETAG], "W/\"abc\"");
+        assert_eq!(resp.headers()[header::LAST_MODIFIED], "Mon, 19 Oct 2026 12:00:00 GMT");
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-cache");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+
+        let resp = respond(&HeaderMap::new(), entry("abc"));
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::ETAG], "\"abc\"");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"{\"ok\":true}");
+    }
+
+    #[tokio::test]
+    async fn legacy_entry_without_validators() {
+        let resp = respond(&HeaderMap::new(), serde_json::json!({ "ok": true }));
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::ETAG).is_none());
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"{\"ok\":true}");
+    }
+}
@@ -0,0 +1,91 @@
+use serde_json::{json, Value};
+
+/// Разница двух JSON-документов: список операций `{op, path, old, new}`,
+/// `path` — JSON Pointer. Объекты сравниваются по ключам, массивы одной
+/// длины — поэлементно, остальное заменяется целиком.
+pub fn json_diff(old: &Value, new: &Value) -> Vec<Value> {
+    let mut out = Vec::new();
+    walk("", old, new, &mut out);
+    out
+}
+
+fn walk(path: &str, old: &Value, new: &Value, out: &mut Vec<Value>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (k, va) in a {
+                let p = format!("{path}/{}", escape(k));
+                match b.get(k) {
+                    Some(vb) => walk(&p, va, vb, out),
+                    None => out.push(json!({ "op": "remove", "path": p, "old": va })),
+                }
+            }
+            for (k, vb) in b.iter().filter(|(k, _)| !a.contains_key(*k)) {
+                out.push(json!({ "op": "add", "path": format!("{path}/{}", escape(k)), "new": vb }));
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (va, vb)) in a.iter().zip(b).enumerate() {
+                walk(&format!("{path}/{i}"), va, vb, out);
+            }
+        }
+        _ if old != new => out.push(json!({ "op": "replace", "path": path, "old": old, "new": new })),
+        _ => {}
+    }
+}
+
+// RFC 6901: `~` → `~0`, `/` → `~1`
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_documents_have_no_diff() {
+        let v = json!({ "a": [1, { "b": null }], "c": "x" });
+        assert!(json_diff(&v, &v.clone()).is_empty());
+    }
+
+    #[test]
+    fn object_add_remove_replace() {
+        // ключи serde_json::Map идут по алфавиту; добавленные — после остальных
+        let old = json!({ "gone": 1, "nested": { "x": 1 }, "title": "A" });
+        let new = json!({ "added": true, "nested": { "x": 2 }, "title": "B" });
+        assert_eq!(json_diff(&old, &new), vec![
+            json!({ "op": "remove", "path": "/gone", "old": 1 }),
+            json!({ "op": "replace", "path": "/nested/x", "old": 1, "new": 2 }),
+            json!({ "op": "replace", "path": "/title", "old": "A", "new": "B" }),
+            json!({ "op": "add", "path": "/added", "new": true }),
+        ]);
+    }
+
+    #[test]
+    fn arrays_by_index_or_whole() {
+        assert_eq!(json_diff(&json!({ "a": [1, 2, 3] }), &json!({ "a": [1, 5, 3] })), vec![
+            json!({ "op": "replace", "path": "/a/1", "old": 2, "new": 5 }),
+        ]);
+        // разная длина — массив заменяется целиком
+        assert_eq!(json_diff(&json!({ "a": [1, 2] }), &json!({ "a": [1, 2, 3] })), vec![
+            json!({ "op": "replace", "path": "/a", "old": [1, 2], "new": [1, 2, 3] }),
+        ]);
+    }
+
+    #[test]
+    fn type_change_and_root_replace() {
+        assert_eq!(json_diff(&json!({ "a": { "b": 1 } }), &json!({ "a": "b" })), vec![
+            json!({ "op": "replace", "path": "/a", "old": { "b": 1 }, "new": "b" }),
+        ]);
+        assert_eq!(json_diff(&Value::Null, &json!({ "a": 1 })), vec![
+            json!({ "op": "replace", "path": "", "old": null, "new": { "a": 1 } }),
+        ]);
+    }
+
+    #[test]
+    fn escapes_pointer_tokens() {
+        assert_eq!(json_diff(&json!({ "a/b": { "~c": 1 } }), &json!({ "a/b": { "~c": 2 } })), vec![
+            json!({ "op": "replace", "path": "/a~1b/~0c", "old": 1, "new": 2 }),
+        ]);
+    }
+}
@@ -0,0 +1,136 @@
+use serde_json::Value;
+
+/// Кольцо полигона: вершины (lon, lat) в порядке GeoJSON.
+pub type Ring = Vec<(f64, f64)>;
+/// Полигон: внешнее кольцо и, возможно, дыры.
+pub type Polygon = Vec<Ring>;
+
+pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let rlat1 = lat1.to_radians();
+    let rlat2 = lat2.to_radians();
+    let dlat = (lat2 - lat1).to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + rlat1.cos() * rlat2.cos() * (dlon / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    6371.0 * c
+}
+
+/// Разбирает GeoJSON `Polygon`/`MultiPolygon` (в том числе внутри `Feature`).
+pub fn parse_polygons(v: &Value) -> Result<Vec<Polygon>, String> {
+    let geom = if v.get("type").and_then(Value::as_str) == Some("Feature") {
+        v.get("geometry").ok_or("Feature without geometry")?
+    } else {
+        v
+    };
+    let coords = geom.get("coordinates").ok_or("geometry without coordinates")?;
+    match geom.get("type").and_then(Value::as_str) {
+        Some("Polygon") => Ok(vec![parse_polygon(coords)?]),
+        Some("MultiPolygon") => coords
+            .as_array()
+            .ok_or("MultiPolygon coordinates must be an array")?
+            .iter()
+            .map(parse_polygon)
+            .collect(),
+        Some(t) => Err(format!("unsupported geometry type {t}")),
+        None => Err("geometry without type".to_string()),
+    }
+}
+
+fn parse_polygon(v: &Value) -> Result<Polygon, String> {
+    let rings = v.as_array().ok_or("polygon must be an array of rings")?;
+    if rings.is_empty() {
+        return Err("polygon without rings".to_string());
+    }
+    rings.iter().map(|ring| {
+        let pts = ring.as_array().ok_or("ring must be an array of positions")?;
+        let ring: Ring = pts.iter().map(|p| {
+            match (p.get(0).and_then(Value::as_f64), p.get(1).and_then(Value::as_f64)) {
+                (Some(lon), Some(lat)) => Ok((lon, lat)),
+                _ => Err(format!("bad position {p}")),
+            }
+        }).collect::<Result<_, String>>()?;
+        if ring.len() < 4 {
+            return Err("ring must have at least 4 positions".to_string());
+        }
+        Ok(ring)
+    }).collect()
+}
+
+/// Попадает ли точка в один из полигонов (дыры исключаются).
+pub fn contains(polygons: &[Polygon], lon: f64, lat: f64) -> bool {
+    polygons.iter().any(|poly| {
+        let mut rings = poly.iter();
+        match rings.next() {
+            Some(outer) => ring_contains(outer, lon, lat) && !rings.any(|hole| ring_contains(hole, lon, lat)),
+            None => false,
+        }
+    })
+}
+
+// Классический ray casting по плоскости lon/lat.
+fn ring_contains(ring: &Ring, lon: f64, lat: f64) -> bool {
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+    for i in 0..ring.len() {
+        let (xi, yi) = ring[i];
+        let (xj, yj) = ring[j];
+        if (yi > lat) != (yj > lat) && lon < (xj - xi) * (lat - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(lon0: f64, lat0: f64, lon1: f64, lat1: f64) -> Ring {
+        vec![(lon0, lat0), (lon1, lat0), (lon1, lat1), (lon0, lat1), (lon0, lat0)]
+    }
+
+    #[test]
+    fn point_in_polygon_with_hole() {
+        let polys = vec![vec![square(0.0, 0.0, 10.0, 10.0), square(4.0, 4.0, 6.0, 6.0)]];
+        assert!(contains(&polys, 2.0, 2.0));
+        assert!(!contains(&polys, 5.0, 5.0));
+        assert!(!contains(&polys, 12.0, 5.0));
+    }
+
+    #[test]
+    fn any_polygon_of_multipolygon() {
+        let polys = vec![vec![square(0.0, 0.0, 1.0, 1.0)], vec![square(-20.0, -20.0, -10.0, -10.0)]];
+        assert!(contains(&polys, -15.0, -15.0));
+        assert!(!contains(&polys, 5.0, 5.0));
+        assert!(!contains(&[], 0.5, 0.5));
+    }
+
+    #[test]
+    fn concave_ring() {
+        // «П»: выемка сверху по центру
+        let ring = vec![(0.0, 0.0), (3.0, 0.0), (3.0, 3.0), (2.0, 3.0), (2.0, 1.0), (1.0, 1.0), (1.0, 3.0), (0.0, 3.0), (0.0, 0.0)];
+        let polys = vec![vec![ring]];
+        assert!(contains(&polys, 0.5, 2.5));
+        assert!(!contains(&polys, 1.5, 2.0));
+    }
+
+    #[test]
+    fn parses_feature_and_multipolygon() {
+        let f = serde_json::json!({ "type": "Feature", "geometry": {
+            "type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 0]]] } });
+        assert_eq!(parse_polygons(&f).unwrap(), vec![vec![vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 0.0)]]]);
+        let mp = serde_json::json!({ "type": "MultiPolygon", "coordinates": [[[[0, 0], [1, 0], [1, 1], [0, 0]]], [[[5, 5], [6, 5], [6, 6], [5, 5]]]] });
+        assert_eq!(parse_polygons(&mp).unwrap().len(), 2);
+        assert!(parse_polygons(&serde_json::json!({ "type": "Polygon", "coordinates": [[[0, 0], [1, 1], [0, 0]]] })).is_err());
+        assert!(parse_polygons(&serde_json::json!({ "type": "Point", "coordinates": [0, 0] })).is_err());
+    }
+
+    #[test]
+    fn haversine_known_distances() {
+        // градус дуги большого круга ~111.19 км
+        assert!((haversine_km(0.0, 0.0, 0.0, 1.0) - 111.19).abs() < 0.01);
+        assert!((haversine_km(0.0, 0.0, 0.0, 180.0) - 20_015.09).abs() < 0.1);
+        assert_eq!(haversine_km(10.0, 20.0, 10.0, 20.0), 0.0);
+    }
+}
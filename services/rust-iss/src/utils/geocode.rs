@@ -0,0 +1,144 @@
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::utils::geo::{contains, parse_polygons, Polygon};
+use crate::utils::helpers::s_pick;
+
+/// Упрощённые границы стран, морей и океанов, вшитые в бинарник.
+const BUNDLED: &str = include_str!("../../data/world_simplified.geojson");
+
+/// Куда попадает подспутниковая точка.
+#[derive(Debug, Clone, Serialize)]
+pub struct Place {
+    pub name: String,
+    pub iso: Option<String>,
+    /// country | land | sea | ocean
+    pub kind: String,
+}
+
+struct Area {
+    place: Place,
+    polygons: Vec<Polygon>,
+    bbox: (f64, f64, f64, f64),
+}
+
+static AREAS: OnceLock<Vec<Area>> = OnceLock::new();
+
+/// Страна/море/океан по координатам, без сетевых запросов.
+/// Данные можно заменить своим GeoJSON через `GEO_BOUNDARIES_PATH`
+/// (понимаются и свойства Natural Earth: `ADMIN`/`NAME`, `ISO_A2`).
+pub fn lookup(lat: f64, lon: f64) -> Option<Place> {
+    find(AREAS.get_or_init(load), lat, lon)
+}
+
+fn find(areas: &[Area], lat: f64, lon: f64) -> Option<Place> {
+    areas.iter()
+        .find(|a| {
+            let (x0, y0, x1, y1) = a.bbox;
+            lon >= x0 && lon <= x1 && lat >= y0 && lat <= y1 && contains(&a.polygons, lon, lat)
+        })
+        .map(|a| a.place.clone())
+}
+
+fn load() -> Vec<Area> {
+    let custom = std::env::var("GEO_BOUNDARIES_PATH").ok().and_then(|path| {
+        std::fs::read_to_string(&path)
+            .inspect_err(|e| tracing::warn!("GEO_BOUNDARIES_PATH {path}: {e}, using bundled boundaries"))
+            .ok()
+    });
+    let areas = parse_areas(custom.as_deref().unwrap_or(BUNDLED));
+    tracing::info!("geocoder: {} areas loaded", areas.len());
+    areas
+}
+
+// Порядок проверки: суша (мелкие раньше крупных), затем моря, затем океаны.
+fn parse_areas(text: &str) -> Vec<Area> {
+    let fc: Value = match serde_json::from_str(text) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("geocoder: bad GeoJSON: {e}");
+            return Vec::new();
+        }
+    };
+    let mut areas: Vec<(u8, f64, Area)> = fc["features"].as_array().into_iter().flatten()
+        .filter_map(|f| {
+            let props = &f["properties"];
+            let name = s_pick(props, &["name", "ADMIN", "NAME"])?;
+            let iso = s_pick(props, &["iso", "ISO_A2"]).filter(|s| s != "-99");
+            let kind = s_pick(props, &["kind"]).unwrap_or_else(|| "country".to_string());
+            let polygons = parse_polygons(f)
+                .inspect_err(|e| tracing::warn!("geocoder: skipping {name}: {e}"))
+                .ok()?;
+            let tier = match kind.as_str() { "sea" => 1, "ocean" => 2, _ => 0 };
+            let (bbox, size) = bounds(&polygons);
+            Some((tier, size, Area { place: Place { name, iso, kind }, polygons, bbox }))
+        })
+        .collect();
+    areas.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    areas.into_iter().map(|(_, _, a)| a).collect()
+}
+
+// Рамка и её площадь в градусах — площадь нужна только для порядка.
+fn bounds(polygons: &[Polygon]) -> ((f64, f64, f64, f64), f64) {
+    let mut b = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for &(x, y) in polygons.iter().filter_map(|p| p.first()).flatten() {
+        b = (b.0.min(x), b.1.min(y), b.2.max(x), b.3.max(y));
+    }
+    (b, (b.2 - b.0) * (b.3 - b.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(areas: &[Area], lat: f64, lon: f64) -> Option<(String, Option<String>, String)> {
+        find(areas, lat, lon).map(|p| (p.name, p.iso, p.kind))
+    }
+
+    #[test]
+    fn bundled_known_points() {
+        let areas = parse_areas(BUNDLED);
+        assert!(!areas.is_empty());
+        // Москва
+        let (_, iso, kind) = at(&areas, 55.75, 37.62).unwrap();
+        assert_eq!((iso.as_deref(), kind.as_str()), (Some("RU"), "country"));
+        // середина Атлантики
+        let (name, iso, kind) = at(&areas, 30.0, -40.0).unwrap();
+        assert_eq!((name.as_str(), iso, kind.as_str()), ("Atlantic Ocean", None, "ocean"));
+        // между Ливией и Грецией: море, хотя полигон Атлантики его тоже накрывает
+        let (name, _, kind) = at(&areas, 34.5, 20.0).unwrap();
+        assert_eq!((name.as_str(), kind.as_str()), ("Mediterranean Sea", "sea"));
+    }
+
+    #[test]
+    fn antimeridian_polygon_split_in_two() {
+        // по RFC 7946 полигон через ±180 режется на две части
+        let text = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"name": "Fiji", "iso": "FJ"}, "geometry": {"type": "MultiPolygon", "coordinates": [
+                [[[177, -19], [180, -19], [180, -16], [177, -16], [177, -19]]],
+                [[[-180, -19], [-178, -19], [-178, -16], [-180, -16], [-180, -19]]]]}},
+            {"type": "Feature", "properties": {"name": "Pacific Ocean", "kind": "ocean"}, "geometry": {"type": "Polygon", "coordinates":
+                [[[-180, -60], [180, -60], [180, 60], [-180, 60], [-180, -60]]]}}]}"#;
+        let areas = parse_areas(text);
+        assert_eq!(at(&areas, -17.5, 179.5).unwrap().1.as_deref(), Some("FJ"));
+        assert_eq!(at(&areas, -17.5, -179.5).unwrap().1.as_deref(), Some("FJ"));
+        assert_eq!(at(&areas, -17.5, -170.0).unwrap().0, "Pacific Ocean");
+        assert_eq!(at(&areas, -17.5, 170.0).unwrap().0, "Pacific Ocean");
+        assert!(at(&areas, 70.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn natural_earth_properties() {
+        let text = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"ADMIN": "Somaliland", "ISO_A2": "-99"}, "geometry": {"type": "Polygon", "coordinates":
+                [[[43, 8], [48, 8], [48, 11], [43, 11], [43, 8]]]}},
+            {"type": "Feature", "properties": {"NAME": "Kenya", "ISO_A2": "KE"}, "geometry": {"type": "Polygon", "coordinates":
+                [[[34, -4], [41, -4], [41, 4], [34, 4], [34, -4]]]}}]}"#;
+        let areas = parse_areas(text);
+        assert_eq!(at(&areas, 9.5, 45.0).unwrap(), ("Somaliland".to_string(), None, "country".to_string()));
+        assert_eq!(at(&areas, 0.0, 37.0).unwrap().1.as_deref(), Some("KE"));
+        assert!(parse_areas("not json").is_empty());
+    }
+}
@@ -1,4 +1,3 @@
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use serde_json::Value;
 
 pub fn s_pick(v: &Value, keys: &[&str]) -> Option<String> {
@@ -11,18 +10,8 @@ pub fn s_pick(v: &Value, keys: &[&str]) -> Option<String> {
     None
 }
 
-pub fn t_pick(v: &Value, keys: &[&str]) -> Option<DateTime<Utc>> {
-    for k in keys {
-        if let Some(x) = v.get(*k) {
-            if let Some(s) = x.as_str() {
-                if let Ok(dt) = s.parse::<DateTime<Utc>>() { return Some(dt); }
-                if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
-                    return Some(Utc.from_utc_datetime(&ndt));
-                }
-            } else if let Some(n) = x.as_i64() {
-                return Some(Utc.timestamp_opt(n, 0).single().unwrap_or_else(Utc::now));
-            }
-        }
-    }
+pub fn num(v: &Value) -> Option<f64> {
+    if let Some(x) = v.as_f64() { return Some(x); }
+    if let Some(s) = v.as_str() { return s.parse::<f64>().ok(); }
     None
-}
\ No newline at end of file
+}
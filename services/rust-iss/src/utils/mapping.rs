@@ -0,0 +1,386 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::utils::helpers::{num, s_pick};
+
+/// Правила по умолчанию, вшитые в бинарник.
+const BUNDLED: &str = include_str!("../../data/field_mappings.json");
+
+/// Правила по источникам (`osdr_catalog`, `osdr_metadata`, `iss_<провайдер>`, ...).
+pub type Mappings = BTreeMap<String, SourceMapping>;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SourceMapping {
+    /// Где в документе лежит запись: первый существующий JSON Pointer,
+    /// `{var}` подставляются (например `{dataset_id}`). Пусто — весь документ.
+    #[serde(default)]
+    pub root: Vec<String>,
+    pub fields: BTreeMap<String, FieldRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldRule {
+    /// JSON Pointer'ы в порядке приоритета: берётся первый, что приводится к типу.
+    pub paths: Vec<String>,
+    #[serde(default, rename = "type")]
+    pub kind: FieldType,
+    /// Форматы chrono для строковых дат, помимо RFC 3339.
+    #[serde(default)]
+    pub formats: Vec<String>,
+    /// list: разделитель, если пришла строка (по умолчанию запятая).
+    pub split: Option<String>,
+    /// list: склеить в строку через этот разделитель.
+    pub join: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    #[default]
+    String,
+    Number,
+    List,
+    Timestamp,
+}
+
+#[derive(Debug, Clone)]
+pub enum Mapped {
+    Text(String),
+    Number(f64),
+    List(Vec<String>),
+    Time(DateTime<Utc>),
+}
+
+/// Результат правила на одной записи — для проверки правил по данным.
+pub enum Outcome {
+    /// Значение взято из `paths[i]`.
+    Matched(usize, Mapped),
+    /// Путь `paths[i]` есть, но значение не приводится к типу (и дальше ничего не нашлось).
+    Unparsable(usize),
+    Missing,
+}
+
+static MAPPINGS: OnceLock<Mappings> = OnceLock::new();
+
+/// Правила источника; `FIELD_MAPPINGS_PATH` подменяет вшитые,
+/// источники, которых там нет, берутся из вшитых.
+pub fn source(name: &str) -> &'static SourceMapping {
+    find(name).unwrap_or_else(|| panic!("no field mapping for source {name}"))
+}
+
+/// Как `source`, но без паники — для источников, которые задаёт только пользователь.
+pub fn find(name: &str) -> Option<&'static SourceMapping> {
+    MAPPINGS.get_or_init(load).get(name)
+}
+
+/// Действующие правила без подстраховки — для `validate-mappings`:
+/// ошибка чтения или разбора `FIELD_MAPPINGS_PATH` возвращается как есть.
+pub fn load_configured() -> anyhow::Result<Mappings> {
+    let mut mappings = bundled();
+    if let Ok(path) = std::env::var("FIELD_MAPPINGS_PATH") {
+        let text = std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+        let custom: Mappings = serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+        mappings.extend(custom);
+    }
+    Ok(mappings)
+}
+
+fn bundled() -> Mappings {
+    serde_json::from_str(BUNDLED).expect("bundled field_mappings.json is valid")
+}
+
+fn load() -> Mappings {
+    load_configured().unwrap_or_else(|e| {
+        tracing::warn!("FIELD_MAPPINGS_PATH {e}, using bundled mappings");
+        bundled()
+    })
+}
+
+impl SourceMapping {
+    pub fn resolve_root<'a>(&self, doc: &'a Value, vars: &[(&str, &str)]) -> &'a Value {
+        self.root.iter()
+            .find_map(|p| {
+                let p = vars.iter().fold(p.clone(), |p, (k, v)| {
+                    p.replace(&format!("{{{k}}}"), &v.replace('~', "~0").replace('/', "~1"))
+                });
+                doc.pointer(&p)
+            })
+            .unwrap_or(doc)
+    }
+
+    pub fn apply(&self, record: &Value) -> Record {
+        let values = self.fields.iter()
+            .filter_map(|(name, rule)| match rule.eval(record) {
+                Outcome::Matched(_, v) => Some((name.clone(), v)),
+                _ => None,
+            })
+            .collect();
+        Record { values }
+    }
+
+    /// Ошибки конфигурации: нет правил для нужных полей, тип не тот, кривые пути.
+    pub fn check(&self, targets: &[(&str, FieldType)]) -> Vec<String> {
+        let mut errors = Vec::new();
+        for p in &self.root {
+            if !p.is_empty() && !p.starts_with('/') {
+                errors.push(format!("root {p:?} is not a JSON Pointer"));
+            }
+        }
+        for (name, want) in targets {
+            let Some(rule) = self.fields.get(*name) else {
+                errors.push(format!("{name}: no rule"));
+                continue;
+            };
+            let got = rule.output_type();
+            if got != *want && !(*want == FieldType::String && got == FieldType::Number) {
+                errors.push(format!("{name}: rule yields {got:?}, expected {want:?}"));
+            }
+        }
+        for (name, rule) in &self.fields {
+            if !targets.iter().any(|(t, _)| t == name) {
+                errors.push(format!("{name}: not a known field"));
+            }
+            if rule.paths.is_empty() {
+                errors.push(format!("{name}: no paths"));
+            }
+            for p in rule.paths.iter().filter(|p| !p.is_empty() && !p.starts_with('/')) {
+                errors.push(format!("{name}: {p:?} is not a JSON Pointer"));
+            }
+            if !rule.formats.is_empty() && rule.kind != FieldType::Timestamp {
+                errors.push(format!("{name}: formats only apply to timestamp"));
+            }
+            if (rule.split.is_some() || rule.join.is_some()) && rule.kind != FieldType::List {
+                errors.push(format!("{name}: split/join only apply to list"));
+            }
+        }
+        errors
+    }
+}
+
+impl FieldRule {
+    pub fn eval(&self, record: &Value) -> Outcome {
+        let mut unparsable = None;
+        for (i, path) in self.paths.iter().enumerate() {
+            let Some(v) = record.pointer(path) else { continue };
+            if v.is_null() || v.as_str().is_some_and(|s| s.trim().is_empty()) {
+                continue;
+            }
+            match self.coerce(v) {
+                Some(m) => return Outcome::Matched(i, m),
+                None => { unparsable.get_or_insert(i); }
+            }
+        }
+        unparsable.map_or(Outcome::Missing, Outcome::Unparsable)
+    }
+
+    fn output_type(&self) -> FieldType {
+        if self.kind == FieldType::List && self.join.is_some() { FieldType::String } else { self.kind }
+    }
+
+    fn coerce(&self, v: &Value) -> Option<Mapped> {
+        match self.kind {
+            FieldType::String => match v {
+                Value::String(s) => Some(Mapped::Text(s.clone())),
+                Value::Number(_) | Value::Bool(_) => Some(Mapped::Text(v.to_string())),
+                _ => None,
+            },
+            FieldType::Number => num(v).map(Mapped::Number),
+            FieldType::List => {
+                let items: Vec<String> = match v {
+                    Value::Array(a) => a.iter()
+                        .filter_map(|x| x.as_str().map(String::from).or_else(|| s_pick(x, &["name"])))
+                        .collect(),
+                    Value::String(s) => s.split(self.split.as_deref().unwrap_or(","))
+                        .map(str::trim).filter(|s| !s.is_empty()).map(String::from)
+                        .collect(),
+                    _ => return None,
+                };
+                if items.is_empty() {
+                    return None;
+                }
+                Some(match &self.join {
+                    Some(sep) => Mapped::Text(items.join(sep)),
+                    None => Mapped::List(items),
+                })
+            }
+            FieldType::Timestamp => parse_time(v, &self.formats).map(Mapped::Time),
+        }
+    }
+}
+
+fn parse_time(v: &Value, formats: &[String]) -> Option<DateTime<Utc>> {
+    if let Some(s) = v.as_str() {
+        if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+            return Some(dt.with_timezone(&Utc));
+        }
+        return formats.iter().find_map(|f| {
+            NaiveDateTime::parse_from_str(s, f).ok()
+                .or_else(|| NaiveDate::parse_from_str(s, f).ok().and_then(|d| d.and_hms_opt(0, 0, 0)))
+                .map(|ndt| Utc.from_utc_datetime(&ndt))
+        });
+    }
+    Utc.timestamp_opt(v.as_f64()? as i64, 0).single()
+}
+
+/// Значения полей одной записи после применения правил.
+pub struct Record {
+    values: BTreeMap<String, Mapped>,
+}
+
+impl Record {
+    pub fn text(&self, field: &str) -> Option<String> {
+        match self.values.get(field)? {
+            Mapped::Text(s) => Some(s.clone()),
+            Mapped::Number(n) => Some(n.to_string()),
+            Mapped::List(l) => Some(l.join(", ")),
+            Mapped::Time(t) => Some(t.to_rfc3339()),
+        }
+    }
+
+    pub fn number(&self, field: &str) -> Option<f64> {
+        match self.values.get(field)? {
+            Mapped::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn list(&self, field: &str) -> Vec<String> {
+        match self.values.get(field) {
+            Some(Mapped::List(l)) => l.clone(),
+            Some(Mapped::Text(s)) => vec![s.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn time(&self, field: &str) -> Option<DateTime<Utc>> {
+        match self.values.get(field)? {
+            Mapped::Time(t) => Some(*t),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(paths: &[&str], kind: FieldType) -> FieldRule {
+        FieldRule { paths: paths.iter().map(|p| p.to_string()).collect(), kind, formats: Vec::new(), split: None, join: None }
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, s).unwrap()
+    }
+
+    #[test]
+    fn eval_takes_first_usable_path() {
+        let r = rule(&["/a", "/b", "/c"], FieldType::String);
+        // null и пустая строка пропускаются
+        match r.eval(&json!({ "a": null, "b": "  ", "c": "x" })) {
+            Outcome::Matched(2, Mapped::Text(s)) => assert_eq!(s, "x"),
+            _ => panic!("expected /c"),
+        }
+        assert!(matches!(r.eval(&json!({})), Outcome::Missing));
+        // путь есть, но значение не приводится — запоминается первый такой
+        let r = rule(&["/a", "/b"], FieldType::Number);
+        assert!(matches!(r.eval(&json!({ "a": "abc", "b": [1] })), Outcome::Unparsable(0)));
+        assert!(matches!(r.eval(&json!({ "a": "abc", "b": "4.5" })), Outcome::Matched(1, Mapped::Number(n)) if n == 4.5));
+    }
+
+    #[test]
+    fn coerces_by_type() {
+        let text = rule(&[], FieldType::String);
+        assert!(matches!(text.coerce(&json!(42)), Some(Mapped::Text(s)) if s == "42"));
+        assert!(text.coerce(&json!({ "a": 1 })).is_none());
+
+        let mut list = rule(&[], FieldType::List);
+        assert!(matches!(list.coerce(&json!("a, b,,c")), Some(Mapped::List(l)) if l == ["a", "b", "c"]));
+        assert!(matches!(list.coerce(&json!(["x", { "name": "y" }, 3])), Some(Mapped::List(l)) if l == ["x", "y"]));
+        assert!(list.coerce(&json!([])).is_none());
+        list.split = Some(";".into());
+        list.join = Some(" | ".into());
+        assert!(matches!(list.coerce(&json!("a;b")), Some(Mapped::Text(s)) if s == "a | b"));
+    }
+
+    #[test]
+    fn parses_times() {
+        let formats = vec!["%Y-%m-%d %H:%M:%S".to_string(), "%Y-%m-%d".to_string()];
+        assert_eq!(parse_time(&json!("2024-01-02T03:04:05+03:00"), &[]), Some(utc(2024, 1, 2, 0, 4, 5)));
+        assert_eq!(parse_time(&json!("2024-01-02 03:04:05"), &formats), Some(utc(2024, 1, 2, 3, 4, 5)));
+        assert_eq!(parse_time(&json!("2024-01-02"), &formats), Some(utc(2024, 1, 2, 0, 0, 0)));
+        assert_eq!(parse_time(&json!("2024-01-02"), &[]), None);
+        assert_eq!(parse_time(&json!(1_700_000_000), &[]), Some(utc(2023, 11, 14, 22, 13, 20)));
+        assert_eq!(parse_time(&json!(1_700_000_000.7), &[]), Some(utc(2023, 11, 14, 22, 13, 20)));
+        assert_eq!(parse_time(&json!("1700000000"), &["%s".to_string()]), Some(utc(2023, 11, 14, 22, 13, 20)));
+        assert_eq!(parse_time(&json!(true), &formats), None);
+    }
+
+    #[test]
+    fn resolve_root_escapes_vars() {
+        let m = SourceMapping { root: vec!["/{id}/metadata".into(), "/metadata".into()], fields: BTreeMap::new() };
+        let doc = json!({ "a/b~c": { "metadata": { "t": 1 } }, "metadata": { "t": 2 } });
+        assert_eq!(m.resolve_root(&doc, &[("id", "a/b~c")]), &json!({ "t": 1 }));
+        // нет записи под id — следующий путь, а без совпадений — весь документ
+        assert_eq!(m.resolve_root(&doc, &[("id", "zzz")]), &json!({ "t": 2 }));
+        assert_eq!(m.resolve_root(&json!({ "x": 1 }), &[("id", "zzz")]), &json!({ "x": 1 }));
+    }
+
+    #[test]
+    fn apply_builds_record() {
+        let mut fields = BTreeMap::new();
+        fields.insert("title".to_string(), rule(&["/title"], FieldType::String));
+        fields.insert("n".to_string(), rule(&["/n"], FieldType::Number));
+        let mut when = rule(&["/when"], FieldType::Timestamp);
+        when.formats = vec!["%Y-%m-%d".into()];
+        fields.insert("when".to_string(), when);
+        let m = SourceMapping { root: Vec::new(), fields };
+        let rec = m.apply(&json!({ "title": "T", "n": "7", "when": "2024-05-06" }));
+        assert_eq!(rec.text("title").as_deref(), Some("T"));
+        assert_eq!(rec.number("n"), Some(7.0));
+        assert_eq!(rec.time("when"), Some(utc(2024, 5, 6, 0, 0, 0)));
+        assert_eq!(rec.list("title"), vec!["T".to_string()]);
+        assert_eq!(rec.text("missing"), None);
+    }
+
+    #[test]
+    fn check_reports_config_errors() {
+        let mut fields = BTreeMap::new();
+        fields.insert("title".to_string(), rule(&["title"], FieldType::Number));
+        let mut tags = rule(&[], FieldType::String);
+        tags.join = Some(",".into());
+        fields.insert("tags".to_string(), tags);
+        let m = SourceMapping { root: vec!["meta".into()], fields };
+        let errors = m.check(&[("title", FieldType::String), ("date", FieldType::Timestamp)]);
+        assert_eq!(errors, vec![
+            "root \"meta\" is not a JSON Pointer",
+            "date: no rule",
+            "tags: not a known field",
+            "tags: no paths",
+            "tags: split/join only apply to list",
+            "title: \"title\" is not a JSON Pointer",
+        ]);
+        // число годится туда, где ждут строку, и list с join — тоже строка
+        let mut fields = BTreeMap::new();
+        let mut org = rule(&["/o"], FieldType::List);
+        org.join = Some(", ".into());
+        fields.insert("organism".to_string(), org);
+        let m = SourceMapping { root: Vec::new(), fields };
+        assert!(m.check(&[("organism", FieldType::String)]).is_empty());
+        assert_eq!(m.check(&[("organism", FieldType::List)]), vec!["organism: rule yields String, expected List"]);
+    }
+
+    #[test]
+    fn bundled_rules_are_valid() {
+        let m = bundled();
+        for source in ["osdr_catalog", "osdr_metadata", "iss_wheretheiss", "iss_open-notify"] {
+            assert!(m.contains_key(source), "{source}");
+        }
+    }
+}
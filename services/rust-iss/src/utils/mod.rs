@@ -1 +1,8 @@
-pub mod helpers;
\ No newline at end of file
+pub mod conditional;
+pub mod diff;
+pub mod geo;
+pub mod geocode;
+pub mod helpers;
+pub mod mapping;
+pub mod orbit;
+pub mod sun;
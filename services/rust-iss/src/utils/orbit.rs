@@ -0,0 +1,138 @@
+use chrono::{DateTime, Utc};
+
+use crate::utils::sun::{gmst_deg, normalize_lon};
+
+const GM_KM3_S2: f64 = 398_600.441_8;
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Наблюдённая точка орбиты.
+#[derive(Clone, Copy)]
+pub struct Fix {
+    pub at: DateTime<Utc>,
+    pub lat: f64,
+    pub lon: f64,
+    pub altitude: f64,
+}
+
+/// Круговая орбита через точки по краям дыры. Плоскость берётся по двум
+/// последним точкам перед дырой, угловая скорость подгоняется так, чтобы
+/// попасть в первую точку после неё. Прецессию узла не учитываем —
+/// на дырах в несколько часов ошибка порядка десятков километров.
+pub struct CircularOrbit {
+    start: Fix,
+    end: Fix,
+    origin: [f64; 3],
+    normal: [f64; 3],
+    omega: f64,
+}
+
+impl CircularOrbit {
+    pub fn fit(before: Fix, start: Fix, end: Fix) -> Option<CircularOrbit> {
+        let dt = (end.at - start.at).num_milliseconds() as f64 / 1000.0;
+        if dt <= 0.0 || start.at <= before.at {
+            return None;
+        }
+        let origin = inertial(&start);
+        let normal = normalize(cross(inertial(&before), origin))?;
+
+        // угол до конечной точки в плоскости орбиты, 0..2π
+        let e = inertial(&end);
+        let e = normalize(sub(e, scale(normal, dot(e, normal))))?;
+        let phi = dot(cross(origin, e), normal).atan2(dot(origin, e)).rem_euclid(std::f64::consts::TAU);
+
+        let r = EARTH_RADIUS_KM + (start.altitude + end.altitude) / 2.0;
+        let kepler = (GM_KM3_S2 / r.powi(3)).sqrt();
+        let turns = ((kepler * dt - phi) / std::f64::consts::TAU).round().max(0.0);
+        let omega = (phi + turns * std::f64::consts::TAU) / dt;
+        Some(CircularOrbit { start, end, origin, normal, omega })
+    }
+
+    /// Подспутниковая точка в момент `at`; высота — линейно между краями.
+    pub fn position(&self, at: DateTime<Utc>) -> Fix {
+        let t = (at - self.start.at).num_milliseconds() as f64 / 1000.0;
+        let theta = self.omega * t;
+        let p = add(scale(self.origin, theta.cos()), scale(cross(self.normal, self.origin), theta.sin()));
+        let span = (self.end.at - self.start.at).num_milliseconds() as f64 / 1000.0;
+        Fix {
+            at,
+            lat: p[2].clamp(-1.0, 1.0).asin().to_degrees(),
+            lon: normalize_lon(p[1].atan2(p[0]).to_degrees() - gmst_deg(at)),
+            altitude: self.start.altitude + (self.end.altitude - self.start.altitude) * t / span,
+        }
+    }
+}
+
+fn inertial(f: &Fix) -> [f64; 3] {
+    let (la, lo) = (f.lat.to_radians(), (f.lon + gmst_deg(f.at)).to_radians());
+    [la.cos() * lo.cos(), la.cos() * lo.sin(), la.sin()]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 { a[0] * b[0] + a[1] * b[1] + a[2] * b[2] }
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] { [a[0] + b[0], a[1] + b[1], a[2] + b[2]] }
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] { [a[0] - b[0], a[1] - b[1], a[2] - b[2]] }
+fn scale(a: [f64; 3], k: f64) -> [f64; 3] { [a[0] * k, a[1] * k, a[2] * k] }
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(a: [f64; 3]) -> Option<[f64; 3]> {
+    let len = dot(a, a).sqrt();
+    (len > 1e-9).then(|| scale(a, 1.0 / len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::geo::haversine_km;
+    use chrono::{Duration, TimeZone};
+
+    const ALT: f64 = 420.0;
+
+    // «настоящая» круговая орбита МКС: наклонение 51.6°, кеплерова скорость
+    fn truth(t0: DateTime<Utc>, secs: i64) -> Fix {
+        let r = EARTH_RADIUS_KM + ALT;
+        let w = (GM_KM3_S2 / r.powi(3)).sqrt();
+        let inc = 51.6_f64.to_radians();
+        let u = 0.3 + w * secs as f64;
+        let p = [u.cos(), u.sin() * inc.cos(), u.sin() * inc.sin()];
+        let at = t0 + Duration::seconds(secs);
+        Fix {
+            at,
+            lat: p[2].asin().to_degrees(),
+            lon: normalize_lon(p[1].atan2(p[0]).to_degrees() - gmst_deg(at)),
+            altitude: ALT,
+        }
+    }
+
+    #[test]
+    fn round_trip_through_gap() {
+        let t0 = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+        // дыра почти в три витка
+        let orbit = CircularOrbit::fit(truth(t0, -60), truth(t0, 0), truth(t0, 16_000)).unwrap();
+        for secs in [0, 1_000, 5_555, 12_345, 16_000] {
+            let (want, got) = (truth(t0, secs), orbit.position(t0 + Duration::seconds(secs)));
+            let km = haversine_km(want.lat, want.lon, got.lat, got.lon);
+            assert!(km < 1.0, "{secs}s: off by {km} km");
+            assert_eq!(got.altitude, ALT);
+        }
+    }
+
+    #[test]
+    fn degenerate_points() {
+        let t0 = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+        let start = truth(t0, 0);
+        let fix = |secs: i64, lat: f64, lon: f64| Fix { at: t0 + Duration::seconds(secs), lat, lon, altitude: ALT };
+        // поправка на вращение Земли за минуту, чтобы точки совпали в инерциальной системе
+        let spin = gmst_deg(t0) - gmst_deg(t0 - Duration::seconds(60));
+        // та же точка: плоскость не определена
+        let same = fix(-60, start.lat, normalize_lon(start.lon + spin));
+        assert!(CircularOrbit::fit(same, start, truth(t0, 600)).is_none());
+        // диаметрально противоположная — тоже
+        let anti = fix(-60, -start.lat, normalize_lon(start.lon + 180.0 + spin));
+        assert!(CircularOrbit::fit(anti, start, truth(t0, 600)).is_none());
+        // время не идёт вперёд
+        assert!(CircularOrbit::fit(truth(t0, -60), start, fix(0, 10.0, 10.0)).is_none());
+        assert!(CircularOrbit::fit(truth(t0, 60), start, truth(t0, 600)).is_none());
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Средняя высота МКС — когда апстрим её не прислал (open-notify).
+pub const DEFAULT_ALTITUDE_KM: f64 = 420.0;
+
+/// Подсолнечная точка (lat, lon) в момент `at`, точность ~0.01° (алгоритм NOAA/Астрономического ежегодника).
+pub fn subsolar_point(at: DateTime<Utc>) -> (f64, f64) {
+    let n = (at.timestamp_millis() as f64 / 1000.0 - 946_728_000.0) / 86_400.0; // сутки от J2000.0
+    let l = (280.460 + 0.985_647_4 * n).rem_euclid(360.0);
+    let g = (357.528 + 0.985_600_3 * n).rem_euclid(360.0).to_radians();
+    let lambda = (l + 1.915 * g.sin() + 0.020 * (2.0 * g).sin()).to_radians();
+    let eps = (23.439 - 0.000_000_4 * n).to_radians();
+
+    let dec = (eps.sin() * lambda.sin()).asin();
+    let ra = (eps.cos() * lambda.sin()).atan2(lambda.cos()).to_degrees();
+    (dec.to_degrees(), normalize_lon(ra - gmst_deg(at)))
+}
+
+/// Звёздное время по Гринвичу в градусах: поворот Земли относительно инерциальной системы.
+pub fn gmst_deg(at: DateTime<Utc>) -> f64 {
+    let n = (at.timestamp_millis() as f64 / 1000.0 - 946_728_000.0) / 86_400.0;
+    (280.460_618_37 + 360.985_647_366_29 * n).rem_euclid(360.0)
+}
+
+/// Ночная сторона Земли как кольцо (lon, lat): линия терминатора, замкнутая через тёмный полюс.
+pub fn night_ring(at: DateTime<Utc>, step_deg: f64) -> Vec<(f64, f64)> {
+    let (dec, sun_lon) = subsolar_point(at);
+    // в равноденствие tan(dec) → 0, терминатор идёт по меридианам
+    let tan_dec = dec.to_radians().tan();
+    let tan_dec = if tan_dec.abs() < 1e-6 { 1e-6f64.copysign(tan_dec) } else { tan_dec };
+    let steps = (360.0 / step_deg).ceil() as usize;
+
+    let mut ring: Vec<(f64, f64)> = (0..=steps).map(|i| {
+        let lon = (-180.0 + i as f64 * step_deg).min(180.0);
+        let lat = (-(lon - sun_lon).to_radians().cos() / tan_dec).atan().to_degrees();
+        (lon, lat)
+    }).collect();
+    // летом северного полушария в темноте южный полюс, и наоборот
+    let pole = if dec > 0.0 { -90.0 } else { 90.0 };
+    ring.push((180.0, pole));
+    ring.push((-180.0, pole));
+    ring.push(ring[0]);
+    if pole < 0.0 {
+        ring.reverse(); // внешнее кольцо GeoJSON — против часовой
+    }
+    ring
+}
+
+/// Освещён ли спутник на высоте `altitude_km`: модель цилиндрической тени Земли.
+pub fn is_sunlit(at: DateTime<Utc>, lat: f64, lon: f64, altitude_km: f64) -> bool {
+    let (sun_lat, sun_lon) = subsolar_point(at);
+    let s = unit(sun_lat, sun_lon);
+    let r = unit(lat, lon).map(|c| c * (EARTH_RADIUS_KM + altitude_km));
+    let along = r[0] * s[0] + r[1] * s[1] + r[2] * s[2];
+    if along >= 0.0 {
+        return true;
+    }
+    let perp2 = r.iter().map(|c| c * c).sum::<f64>() - along * along;
+    perp2 > EARTH_RADIUS_KM * EARTH_RADIUS_KM
+}
+
+fn unit(lat: f64, lon: f64) -> [f64; 3] {
+    let (la, lo) = (lat.to_radians(), lon.to_radians());
+    [la.cos() * lo.cos(), la.cos() * lo.sin(), la.sin()]
+}
+
+pub fn normalize_lon(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn subsolar_latitude_at_solstices_and_equinox() {
+        let (lat, _) = subsolar_point(utc(2024, 6, 20, 20, 51));
+        assert!((lat - 23.44).abs() < 0.05, "{lat}");
+        let (lat, _) = subsolar_point(utc(2024, 12, 21, 9, 20));
+        assert!((lat + 23.44).abs() < 0.05, "{lat}");
+        let (lat, _) = subsolar_point(utc(2024, 3, 20, 3, 6));
+        assert!(lat.abs() < 0.05, "{lat}");
+    }
+
+    #[test]
+    fn subsolar_longitude_follows_utc_noon() {
+        // в полдень UTC солнце над Гринвичем с точностью до уравнения времени (до ~4°)
+        let (_, lon) = subsolar_point(utc(2024, 6, 13, 12, 0));
+        assert!(lon.abs() < 1.0, "{lon}");
+        let (_, lon) = subsolar_point(utc(2024, 6, 13, 18, 0));
+        assert!((lon + 90.0).abs() < 1.0, "{lon}");
+        // начало ноября: уравнение времени ~+16 мин, истинный полдень в Гринвиче
+        // в 11:44 UTC — к 12:00 солнце уже на ~4° западнее
+        let (_, lon) = subsolar_point(utc(2024, 11, 3, 12, 0));
+        assert!((lon + 4.1).abs() < 0.2, "{lon}");
+    }
+
+    #[test]
+    fn shadow_cylinder() {
+        let at = utc(2024, 3, 20, 3, 6);
+        let (lat, lon) = subsolar_point(at);
+        assert!(is_sunlit(at, lat, lon, DEFAULT_ALTITUDE_KM));
+        assert!(!is_sunlit(at, -lat, normalize_lon(lon + 180.0), DEFAULT_ALTITUDE_KM));
+        // за терминатором на 420 км тень начинается примерно через 20°
+        assert!(is_sunlit(at, 0.0, normalize_lon(lon + 100.0), DEFAULT_ALTITUDE_KM));
+        assert!(!is_sunlit(at, 0.0, normalize_lon(lon + 120.0), DEFAULT_ALTITUDE_KM));
+    }
+
+    #[test]
+    fn normalizes_longitude() {
+        assert_eq!(normalize_lon(190.0), -170.0);
+        assert_eq!(normalize_lon(-190.0), 170.0);
+        assert_eq!(normalize_lon(180.0), -180.0);
+        assert_eq!(normalize_lon(45.0), 45.0);
+    }
+}